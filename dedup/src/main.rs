@@ -1,21 +1,412 @@
-use std::io::Write;
-
-fn main() {
-    let input = std::fs::read_to_string("input.txt").unwrap();
-    let mut set = std::collections::HashMap::new();
-    for line in input.lines() {
-        let mut words = line.split_whitespace();
-        let first = words.next().unwrap();
-        let second = words.next().unwrap();
-        if second == "0" {
-            continue;
-        }
-        set.insert(first, second);
-    }
-    // write to output.txt
-    let mut file = std::fs::File::create("output.txt").unwrap();
-    for (key, value) in set {
-        let string = format!("{} {}\n", key, value);
-        file.write_all(string.as_bytes()).unwrap();
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use game_helper_v2::retrograde::Value;
+use game_helper_v2::table::{
+    is_table, TableHeader, TableValue, DEPTH_EXACT, SIDE_PLAYER_1, SIDE_PLAYER_2,
+};
+
+use table::{format_entry, resolve, Entry, Policy, Table};
+
+mod table;
+
+const USAGE: &str = "\
+Usage: dedup <command> [options] <table>...
+
+Tables are text (`BOARD MOVE [VALUE]` lines) or binary, told apart by their
+first bytes. A table either has a value for every entry or for none, and a
+binary table without values says so in its flags.
+
+Commands:
+  dedup <table>             one entry per board
+  merge <table>...          the tables one after the other, one entry per board;
+                            they must be of the same side, depth and kind
+  diff <old> <new>          boards added, removed and changed
+  stats <table>             counts of entries, duplicates, conflicts and values
+  sort <table>              text table sorted by board, duplicates kept
+  convert <table>           table in the other format, or --format
+
+Options:
+  -o, --output <path>       table written by dedup, merge, sort and convert
+  --policy <policy>         entry kept when a board has different ones: first,
+                            last, best (for the side to move) or error (default)
+  --report <path>           write the boards with different entries there
+  --drop-empty              leave out entries without a move
+  --format <format>         text or binary (default: the format of the first table,
+                            the other one for convert)
+  --depth <plies>           depth put in binary headers, for text tables without
+                            exact values
+  --side <side>             side to move, white or black, of text tables that do
+                            not say, needed to merge them and to write them as
+                            binary tables";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    Dedup,
+    Merge,
+    Diff,
+    Stats,
+    Sort,
+    Convert,
+    Help,
+}
+
+struct Options {
+    command: Command,
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    policy: Policy,
+    report: Option<PathBuf>,
+    drop_empty: bool,
+    binary: Option<bool>,
+    depth: Option<u8>,
+    side: Option<bool>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut args = args.iter();
+        let command = match args.next().map(String::as_str) {
+            Some("dedup") => Command::Dedup,
+            Some("merge") => Command::Merge,
+            Some("diff") => Command::Diff,
+            Some("stats") => Command::Stats,
+            Some("sort") => Command::Sort,
+            Some("convert") => Command::Convert,
+            Some("help" | "-h" | "--help") => Command::Help,
+            Some(name) => return Err(format!("unknown command `{}`", name)),
+            None => return Err("missing command".to_string()),
+        };
+        let mut options = Options {
+            command,
+            inputs: vec![],
+            output: None,
+            policy: Policy::Error,
+            report: None,
+            drop_empty: false,
+            binary: None,
+            depth: None,
+            side: None,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    options.command = Command::Help;
+                    continue;
+                }
+                "--drop-empty" => {
+                    options.drop_empty = true;
+                    continue;
+                }
+                _ if !arg.starts_with('-') => {
+                    options.inputs.push(PathBuf::from(arg));
+                    continue;
+                }
+                _ => {}
+            }
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            let value = match inline.or_else(|| args.next().cloned()) {
+                Some(value) => value,
+                None => return Err(format!("missing value for {}", flag)),
+            };
+            let invalid = |what: &str| format!("invalid {} `{}`", what, value);
+            match flag {
+                "-o" | "--output" => options.output = Some(PathBuf::from(value)),
+                "--policy" => options.policy = Policy::parse(&value).ok_or(invalid("policy"))?,
+                "--report" => options.report = Some(PathBuf::from(value)),
+                "--format" => {
+                    options.binary = match value.as_str() {
+                        "text" => Some(false),
+                        "binary" => Some(true),
+                        _ => return Err(invalid("format")),
+                    }
+                }
+                "--depth" => options.depth = Some(value.parse().map_err(|_| invalid("depth"))?),
+                "--side" => {
+                    options.side = match value.as_str() {
+                        "white" | "w" => Some(true),
+                        "black" | "b" => Some(false),
+                        _ => return Err(invalid("side")),
+                    }
+                }
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+
+        let count = options.inputs.len();
+        match options.command {
+            Command::Merge if count == 0 => return Err("merge needs tables".to_string()),
+            Command::Diff if count != 2 => return Err("diff needs two tables".to_string()),
+            Command::Merge | Command::Diff | Command::Help => {}
+            _ if count != 1 => return Err("expected one table".to_string()),
+            _ => {}
+        }
+        Ok(options)
+    }
+
+    fn output(&self) -> Result<&Path, String> {
+        self.output
+            .as_deref()
+            .ok_or_else(|| "missing --output".to_string())
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) if args.is_empty() => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            eprintln!("error: {}\nRun `dedup help` for usage.", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match options.command {
+        Command::Dedup | Command::Merge => dedup(&options),
+        Command::Diff => diff(&options),
+        Command::Stats => stats(&options),
+        Command::Sort => sort(&options),
+        Command::Convert => convert(&options),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load_inputs(options: &Options) -> Result<Table, Box<dyn Error>> {
+    let paths: Vec<&Path> = options.inputs.iter().map(PathBuf::as_path).collect();
+    Table::concat(&paths, options.side)
+}
+
+// Writes `entries` of `table` to `--output`, as a binary table if `binary`.
+fn write(
+    options: &Options,
+    binary: bool,
+    table: &Table,
+    entries: &[Entry],
+) -> Result<(), Box<dyn Error>> {
+    let output = options.output()?;
+    let entries: Vec<Entry> = entries
+        .iter()
+        .filter(|(_, next, _)| !options.drop_empty || next.0 != 0)
+        .copied()
+        .collect();
+    if !binary {
+        table::write_text(output, &entries)?;
+        return Ok(());
+    }
+
+    let header = match table.header {
+        Some(header) => header,
+        None => {
+            let exact = entries
+                .iter()
+                .any(|(_, _, value)| matches!(value, Some(TableValue::Exact(_))));
+            let depth = match options.depth {
+                Some(depth) => depth,
+                None if exact => DEPTH_EXACT,
+                None => return Err("--depth is needed for a binary table".into()),
+            };
+            let side = match options.side.or(table.side) {
+                Some(true) => SIDE_PLAYER_1,
+                Some(false) => SIDE_PLAYER_2,
+                None => return Err("--side is needed for a binary table".into()),
+            };
+            TableHeader::new(depth, side, table.flags)
+        }
+    };
+    table::write_binary(output, header, &entries)?;
+    Ok(())
+}
+
+fn dedup(options: &Options) -> Result<(), Box<dyn Error>> {
+    let table = load_inputs(options)?;
+    let resolved = resolve(&table.entries, options.policy);
+    eprintln!(
+        "{} boards, {} duplicates, {} conflicts",
+        resolved.entries.len(),
+        resolved.duplicates,
+        resolved.conflicts.len()
+    );
+    if let Some(report) = &options.report {
+        table::write_report(report, &resolved.conflicts)?;
+    }
+    if options.policy == Policy::Error && !resolved.conflicts.is_empty() {
+        let first = &resolved.conflicts[0];
+        return Err(format!(
+            "{} boards have different entries, e.g. {} and {}; choose a --policy",
+            resolved.conflicts.len(),
+            format_entry(&first.kept),
+            format_entry(&first.dropped[0])
+        )
+        .into());
+    }
+    let binary = options.binary.unwrap_or(is_table(&options.inputs[0])?);
+    write(options, binary, &table, &resolved.entries)
+}
+
+// Probabilities closer than this are the same, as binary tables keep 14 bits
+// of them and older text tables four digits.
+const PROBABILITY_TOLERANCE: f32 = 1e-3;
+
+fn same(a: &Entry, b: &Entry) -> bool {
+    let values = match (a.2, b.2) {
+        (Some(TableValue::Probability(p)), Some(TableValue::Probability(q))) => {
+            (p - q).abs() <= PROBABILITY_TOLERANCE
+        }
+        (Some(x), Some(y)) => x == y,
+        // a table without values only has moves to compare
+        _ => true,
+    };
+    a.1 == b.1 && values
+}
+
+fn diff(options: &Options) -> Result<(), Box<dyn Error>> {
+    let old = Table::load(&options.inputs[0])?;
+    let new = Table::load(&options.inputs[1])?;
+    let old = resolve(&old.entries, Policy::First);
+    let new = resolve(&new.entries, Policy::First);
+    for (path, resolved) in options.inputs.iter().zip([&old, &new]) {
+        if !resolved.conflicts.is_empty() {
+            eprintln!(
+                "warning: {} has {} boards with different entries, comparing the first ones",
+                path.display(),
+                resolved.conflicts.len()
+            );
+        }
+    }
+
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    let (mut i, mut j) = (0, 0);
+    while i < old.entries.len() || j < new.entries.len() {
+        let a = old.entries.get(i);
+        let b = new.entries.get(j);
+        match (a, b) {
+            (Some(a), Some(b)) if a.0 == b.0 => {
+                if !same(a, b) {
+                    println!("~ {} -> {}", format_entry(a), format_entry(b));
+                    changed += 1;
+                }
+                i += 1;
+                j += 1;
+            }
+            (Some(a), b) if b.is_none_or(|b| a.0 < b.0) => {
+                println!("- {}", format_entry(a));
+                removed += 1;
+                i += 1;
+            }
+            (_, Some(b)) => {
+                println!("+ {}", format_entry(b));
+                added += 1;
+                j += 1;
+            }
+            (_, None) => unreachable!(),
+        }
+    }
+    eprintln!("{} added, {} removed, {} changed", added, removed, changed);
+    Ok(())
+}
+
+fn stats(options: &Options) -> Result<(), Box<dyn Error>> {
+    let path = &options.inputs[0];
+    let table = Table::load(path)?;
+    let resolved = resolve(&table.entries, Policy::First);
+
+    match table.header {
+        Some(header) => println!(
+            "format      binary v{}, depth {}, side {}, flags {}",
+            header.version, header.depth, header.side, header.flags
+        ),
+        None => println!("format      text"),
+    }
+    println!("entries     {}", table.entries.len());
+    println!("boards      {}", resolved.entries.len());
+    println!("duplicates  {}", resolved.duplicates);
+    println!("conflicts   {}", resolved.conflicts.len());
+    println!(
+        "no move     {}",
+        resolved
+            .entries
+            .iter()
+            .filter(|(_, next, _)| next.0 == 0)
+            .count()
+    );
+
+    let (mut wins, mut draws, mut losses) = (0, 0, 0);
+    let mut probabilities = vec![];
+    for (_, _, value) in resolved.entries.iter() {
+        match value {
+            Some(TableValue::Exact(Value::Win(_))) => wins += 1,
+            Some(TableValue::Exact(Value::Draw)) => draws += 1,
+            Some(TableValue::Exact(Value::Loss(_))) => losses += 1,
+            Some(TableValue::Probability(p)) => probabilities.push(*p),
+            None => {}
+        }
+    }
+    if wins + draws + losses > 0 {
+        println!("wins        {}", wins);
+        println!("draws       {}", draws);
+        println!("losses      {}", losses);
+    }
+    if !probabilities.is_empty() {
+        let mean = probabilities.iter().sum::<f32>() / probabilities.len() as f32;
+        let min = probabilities.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = probabilities
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        println!(
+            "probability mean {:.4}, min {:.4}, max {:.4}",
+            mean, min, max
+        );
+    }
+    Ok(())
+}
+
+fn sort(options: &Options) -> Result<(), Box<dyn Error>> {
+    if options.binary == Some(true) {
+        return Err("binary tables are always sorted, use dedup or convert".into());
+    }
+    let table = Table::load(&options.inputs[0])?;
+    let entries: Vec<Entry> = table
+        .entries
+        .iter()
+        .filter(|(_, next, _)| !options.drop_empty || next.0 != 0)
+        .copied()
+        .collect();
+    table::write_text(options.output()?, &entries)?;
+    Ok(())
+}
+
+fn convert(options: &Options) -> Result<(), Box<dyn Error>> {
+    let table = Table::load(&options.inputs[0])?;
+    let resolved = resolve(&table.entries, Policy::First);
+    if !resolved.conflicts.is_empty() {
+        return Err(format!(
+            "{} boards have different entries, run dedup first",
+            resolved.conflicts.len()
+        )
+        .into());
     }
+    // to the other format unless told
+    let binary = options.binary.unwrap_or(!is_table(&options.inputs[0])?);
+    write(options, binary, &table, &resolved.entries)
 }
@@ -0,0 +1,293 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use game_helper_v2::board::Board;
+use game_helper_v2::next_move::NextMove;
+use game_helper_v2::retrograde::Value;
+use game_helper_v2::table::{write_table, TableEntry, TableHeader, TableValue, FLAG_NO_VALUES};
+use game_helper_v2::tablebase::Tablebase;
+
+pub type Entry = (Board, NextMove, Option<TableValue>);
+
+/// A table read whole, entries sorted by board and, for a board listed more
+/// than once, in the order of the files.
+pub struct Table {
+    pub entries: Vec<Entry>,
+    pub header: Option<TableHeader>,
+    pub side: Option<bool>,
+    pub flags: u8,
+}
+
+impl Table {
+    pub fn load(path: &Path) -> Result<Table, Box<dyn Error>> {
+        let tablebase = Tablebase::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Table {
+            entries: tablebase.iter().collect(),
+            header: tablebase.header(),
+            side: tablebase.side(),
+            flags: tablebase.flags(),
+        })
+    }
+
+    // Whether the values are exact results rather than probabilities, `None`
+    // for a table without values.
+    fn exact(&self) -> Option<bool> {
+        self.entries
+            .iter()
+            .find_map(|(_, _, value)| value.map(|value| matches!(value, TableValue::Exact(_))))
+    }
+
+    // Why `other` holds positions that cannot go in the same table as this
+    // one's, if it does.
+    fn mismatch(&self, other: &Table) -> Option<String> {
+        let side_name = |side: bool| if side { "white" } else { "black" };
+        if self.side != other.side {
+            return Some(format!(
+                "side to move {} and {}",
+                self.side.map_or("unknown", side_name),
+                other.side.map_or("unknown", side_name)
+            ));
+        }
+        if let (Some(a), Some(b)) = (self.header, other.header) {
+            if a.rules != b.rules {
+                return Some(format!("rule sets {} and {}", a.rules, b.rules));
+            }
+            if a.depth != b.depth {
+                return Some(format!("depths {} and {}", a.depth, b.depth));
+            }
+        }
+        if (self.flags ^ other.flags) & FLAG_NO_VALUES != 0 {
+            return Some("a table with values and one without".to_string());
+        }
+        if self.flags != other.flags {
+            return Some(format!("flags {} and {}", self.flags, other.flags));
+        }
+        match (self.exact(), other.exact()) {
+            (Some(a), Some(b)) if a != b => Some("exact values and probabilities".to_string()),
+            _ => None,
+        }
+    }
+
+    // The tables of `paths` one after the other, `side` to move in those that
+    // do not say. They must agree on the side to move, the rules, the depth,
+    // the orientation of the positions and the kind of values. The result
+    // keeps the header of the first binary table.
+    pub fn concat(paths: &[&Path], side: Option<bool>) -> Result<Table, Box<dyn Error>> {
+        let mut tables = paths.iter().map(|path| {
+            Table::load(path).map(|mut table| {
+                table.side = table.side.or(side);
+                (path, table)
+            })
+        });
+        let (first_path, mut table) = match tables.next() {
+            Some(table) => table?,
+            None => return Err("no table given".into()),
+        };
+        for other in tables {
+            let (path, other) = other?;
+            if table.side.is_none() {
+                return Err(format!(
+                    "{} does not say its side to move, give --side",
+                    first_path.display()
+                )
+                .into());
+            }
+            if let Some(mismatch) = table.mismatch(&other) {
+                return Err(format!(
+                    "{} and {} cannot be merged: {}",
+                    first_path.display(),
+                    path.display(),
+                    mismatch
+                )
+                .into());
+            }
+            table.entries.extend(other.entries);
+            table.header = table.header.or(other.header);
+        }
+        table.entries.sort_by_key(|(board, _, _)| board.0);
+        Ok(table)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Policy {
+    First,
+    Last,
+    Best,
+    Error,
+}
+
+impl Policy {
+    pub fn parse(name: &str) -> Option<Policy> {
+        match name {
+            "first" => Some(Policy::First),
+            "last" => Some(Policy::Last),
+            "best" => Some(Policy::Best),
+            "error" => Some(Policy::Error),
+            _ => None,
+        }
+    }
+}
+
+// How good `entry` is for the side to move: the shortest win first, then
+// draws, then the longest loss, and probabilities by value. Entries without
+// a move come last.
+fn score(entry: &Entry) -> f64 {
+    let (_, next, value) = entry;
+    if next.0 == 0 {
+        return -2.0;
+    }
+    match value {
+        Some(TableValue::Exact(Value::Win(n))) => 3e6 - *n as f64,
+        Some(TableValue::Exact(Value::Draw)) => 2e6,
+        Some(TableValue::Exact(Value::Loss(n))) => 1e6 + *n as f64,
+        Some(TableValue::Probability(p)) => *p as f64,
+        None => -1.0,
+    }
+}
+
+/// Different entries for one board and the one kept.
+pub struct Conflict {
+    pub kept: Entry,
+    pub dropped: Vec<Entry>,
+}
+
+#[derive(Default)]
+pub struct Resolved {
+    pub entries: Vec<Entry>,
+    pub conflicts: Vec<Conflict>,
+    // entries equal to one kept
+    pub duplicates: usize,
+}
+
+// One entry per board of the sorted `entries`, picking among different ones
+// with `policy`. With `Policy::Error` the first one is kept, the caller is
+// expected to fail if there are conflicts.
+pub fn resolve(entries: &[Entry], policy: Policy) -> Resolved {
+    let mut resolved = Resolved::default();
+    for group in entries.chunk_by(|a, b| a.0 == b.0) {
+        let mut distinct: Vec<Entry> = vec![];
+        for entry in group {
+            if distinct.contains(entry) {
+                resolved.duplicates += 1;
+            } else {
+                distinct.push(*entry);
+            }
+        }
+        let kept = match policy {
+            Policy::First | Policy::Error => distinct[0],
+            Policy::Last => *distinct.last().unwrap(),
+            Policy::Best => *distinct
+                .iter()
+                .rev()
+                .max_by(|a, b| score(a).total_cmp(&score(b)))
+                .unwrap(),
+        };
+        if distinct.len() > 1 {
+            distinct.retain(|entry| *entry != kept);
+            resolved.conflicts.push(Conflict {
+                kept,
+                dropped: distinct,
+            });
+        }
+        resolved.entries.push(kept);
+    }
+    resolved
+}
+
+pub fn format_entry((board, next, value): &Entry) -> String {
+    match value {
+        Some(value) => format!("{:X} {:X} {}", board.0, next, value),
+        None => format!("{:X} {:X}", board.0, next),
+    }
+}
+
+pub fn write_report(path: &Path, conflicts: &[Conflict]) -> io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    for conflict in conflicts {
+        write!(f, "kept {}", format_entry(&conflict.kept))?;
+        for dropped in conflict.dropped.iter() {
+            write!(f, "; dropped {}", format_entry(dropped))?;
+        }
+        writeln!(f)?;
+    }
+    f.flush()
+}
+
+pub fn write_text(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    for entry in entries {
+        writeln!(f, "{}", format_entry(entry))?;
+    }
+    f.flush()
+}
+
+// The entries must all have a value, or none if `header` has
+// `FLAG_NO_VALUES`, as for `generate`'s text tables.
+pub fn write_binary(path: &Path, header: TableHeader, entries: &[Entry]) -> io::Result<()> {
+    let no_values = header.flags & FLAG_NO_VALUES != 0;
+    let entries = entries
+        .iter()
+        .map(|(board, next_move, value)| {
+            let value = match (value, no_values) {
+                (Some(value), false) => *value,
+                // encoded as zero
+                (None, true) => TableValue::Probability(0.0),
+                (Some(_), true) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{:X} has a value in a table without values", board.0),
+                    ))
+                }
+                (None, false) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{:X} has no value in a table with values", board.0),
+                    ))
+                }
+            };
+            Ok(TableEntry {
+                board: *board,
+                next_move: *next_move,
+                value,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    write_table(path, header, entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use game_helper_v2::table::{TableHeader, SIDE_PLAYER_1};
+
+    use super::{write_binary, write_text, Table};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("dedup-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn table_without_values_round_trips() {
+        let text = temp_path("moves.txt");
+        let binary = temp_path("moves.bin");
+        let back = temp_path("back.txt");
+        fs::write(&text, "12345 1A2\nABCDEF 0\n1234567890 3F\n").unwrap();
+
+        let table = Table::load(&text).unwrap();
+        let header = TableHeader::new(6, SIDE_PLAYER_1, table.flags);
+        write_binary(&binary, header, &table.entries).unwrap();
+        let table = Table::load(&binary).unwrap();
+        assert!(table.entries.iter().all(|(_, _, value)| value.is_none()));
+        write_text(&back, &table.entries).unwrap();
+        assert_eq!(fs::read(&back).unwrap(), fs::read(&text).unwrap());
+
+        for path in [text, binary, back] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
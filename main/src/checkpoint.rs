@@ -0,0 +1,226 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use log::warn;
+
+use game_helper_v2::board::Board;
+use game_helper_v2::next_move::NextMove;
+
+// Checkpoints of a `generate` run, in a directory of their own:
+//
+// - `meta.txt`, the parameters of the run, checked on resume
+// - `level_NN.bin`, the boards of depth NN of the frontier
+// - `white_NN.bin` and `black_NN.bin`, what `calc_proba` stored for the
+//   boards of depth NN for that side
+//
+// Each file is written under a temporary name then renamed, so a file that
+// is there is complete. Files start with `MAGIC`, the depth and the number of
+// records, little-endian.
+
+const MAGIC: &[u8; 4] = b"DBCK";
+const HEADER_SIZE: usize = 16;
+const BOARD_SIZE: usize = 8;
+const PROBA_SIZE: usize = 14;
+const META: &str = "meta.txt";
+const VERSION: &str = "1";
+
+// Board, probability and move, as `calc_proba` stores them.
+pub type Probas = Vec<(Board, f32, NextMove)>;
+
+pub struct Checkpoint {
+    dir: PathBuf,
+}
+
+// What a checkpoint must have been made with to be resumed.
+pub struct RunParams {
+    pub root: String,
+    pub depth: u8,
+    pub eval: String,
+}
+
+impl RunParams {
+    fn lines(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("version", VERSION.to_string()),
+            ("root", self.root.clone()),
+            ("depth", self.depth.to_string()),
+            ("eval", self.eval.clone()),
+        ]
+    }
+}
+
+fn side_name(is_player_one: bool) -> &'static str {
+    if is_player_one {
+        "white"
+    } else {
+        "black"
+    }
+}
+
+impl Checkpoint {
+    // Starts checkpointing a new run in `dir`, which must not hold one already.
+    pub fn create(dir: &Path, params: &RunParams) -> Result<Checkpoint, Box<dyn Error>> {
+        if dir.join(META).exists() {
+            return Err(format!(
+                "{} already holds a checkpoint, pass --resume to continue it",
+                dir.display()
+            )
+            .into());
+        }
+        fs::create_dir_all(dir)?;
+        let checkpoint = Checkpoint {
+            dir: dir.to_path_buf(),
+        };
+        let meta: String = params
+            .lines()
+            .iter()
+            .map(|(key, value)| format!("{} {}\n", key, value))
+            .collect();
+        checkpoint.write_atomic(META, meta.as_bytes())?;
+        Ok(checkpoint)
+    }
+
+    // Opens the checkpoint in `dir` after checking it was made for `params`.
+    pub fn resume(dir: &Path, params: &RunParams) -> Result<Checkpoint, Box<dyn Error>> {
+        let meta = fs::read_to_string(dir.join(META))
+            .map_err(|e| format!("no checkpoint in {}: {}", dir.display(), e))?;
+        for (key, expected) in params.lines() {
+            let found = meta
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value);
+            if found != Some(expected.as_str()) {
+                return Err(format!(
+                    "the checkpoint in {} was made with {} `{}`, not `{}`",
+                    dir.display(),
+                    key,
+                    found.unwrap_or(""),
+                    expected
+                )
+                .into());
+            }
+        }
+        Ok(Checkpoint {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    fn write_atomic(&self, name: &str, bytes: &[u8]) -> io::Result<()> {
+        let tmp = self.dir.join(format!("{}.tmp", name));
+        let mut f = BufWriter::new(File::create(&tmp)?);
+        f.write_all(bytes)?;
+        f.into_inner()?.sync_all()?;
+        fs::rename(tmp, self.dir.join(name))
+    }
+
+    fn write_records(&self, name: &str, depth: u8, count: usize, records: &[u8]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + records.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[depth, 0, 0, 0]);
+        bytes.extend_from_slice(&(count as u64).to_le_bytes());
+        bytes.extend_from_slice(records);
+        self.write_atomic(name, &bytes)
+    }
+
+    // Records of `name` if it is a complete file for `depth`.
+    fn read_records(&self, name: &str, depth: u8, record_size: usize) -> Option<Vec<u8>> {
+        let bytes = fs::read(self.dir.join(name)).ok()?;
+        if bytes.len() < HEADER_SIZE || &bytes[0..4] != MAGIC || bytes[4] != depth {
+            warn!("Ignoring invalid checkpoint file {}", name);
+            return None;
+        }
+        let mut count = [0u8; 8];
+        count.copy_from_slice(&bytes[8..16]);
+        if bytes.len() - HEADER_SIZE != u64::from_le_bytes(count) as usize * record_size {
+            warn!("Ignoring truncated checkpoint file {}", name);
+            return None;
+        }
+        Some(bytes[HEADER_SIZE..].to_vec())
+    }
+
+    pub fn save_level<'a, I: Iterator<Item = &'a Board>>(
+        &self,
+        depth: u8,
+        boards: I,
+    ) -> io::Result<()> {
+        let mut boards: Vec<u64> = boards.map(|board| board.0).collect();
+        boards.sort_unstable();
+        let records: Vec<u8> = boards.iter().flat_map(|b| b.to_le_bytes()).collect();
+        self.write_records(
+            &format!("level_{:02}.bin", depth),
+            depth,
+            boards.len(),
+            &records,
+        )
+    }
+
+    // Boards of the frontier from depth 0 up to the first depth missing.
+    pub fn load_levels(&self) -> Vec<Vec<Board>> {
+        let mut levels = vec![];
+        for depth in 0..=u8::MAX {
+            let name = format!("level_{:02}.bin", depth);
+            let records = match self.read_records(&name, depth, BOARD_SIZE) {
+                Some(records) => records,
+                None => break,
+            };
+            levels.push(
+                records
+                    .chunks(BOARD_SIZE)
+                    .map(|chunk| {
+                        let mut board = [0u8; 8];
+                        board.copy_from_slice(chunk);
+                        Board(u64::from_le_bytes(board))
+                    })
+                    .collect(),
+            );
+        }
+        levels
+    }
+
+    pub fn save_probas(
+        &self,
+        is_player_one: bool,
+        depth: u8,
+        probas: &[(Board, f32, NextMove)],
+    ) -> io::Result<()> {
+        let mut records = Vec::with_capacity(probas.len() * PROBA_SIZE);
+        for (board, proba, next) in probas {
+            records.extend_from_slice(&board.0.to_le_bytes());
+            records.extend_from_slice(&proba.to_bits().to_le_bytes());
+            records.extend_from_slice(&next.0.to_le_bytes());
+        }
+        let name = format!("{}_{:02}.bin", side_name(is_player_one), depth);
+        self.write_records(&name, depth, probas.len(), &records)
+    }
+
+    // What `calc_proba` stored for `is_player_one`, by depth, from
+    // `max_depth - 1` down to the last depth it completed.
+    pub fn load_probas(&self, is_player_one: bool, max_depth: u8) -> Vec<(u8, Probas)> {
+        let mut levels = vec![];
+        for depth in (0..max_depth).rev() {
+            let name = format!("{}_{:02}.bin", side_name(is_player_one), depth);
+            let records = match self.read_records(&name, depth, PROBA_SIZE) {
+                Some(records) => records,
+                None => break,
+            };
+            let probas = records
+                .chunks(PROBA_SIZE)
+                .map(|chunk| {
+                    let mut board = [0u8; 8];
+                    board.copy_from_slice(&chunk[0..8]);
+                    let proba = u32::from_le_bytes([chunk[8], chunk[9], chunk[10], chunk[11]]);
+                    (
+                        Board(u64::from_le_bytes(board)),
+                        f32::from_bits(proba),
+                        NextMove(u16::from_le_bytes([chunk[12], chunk[13]])),
+                    )
+                })
+                .collect();
+            levels.push((depth, probas));
+        }
+        levels
+    }
+}
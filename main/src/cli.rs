@@ -0,0 +1,260 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::thread;
+
+use log::LevelFilter;
+
+use game_helper_v2::eval;
+use game_helper_v2::game_state::GameState;
+
+pub const USAGE: &str = "\
+Usage: dobustu-precomp <command> [options]
+
+Commands:
+  generate   depth-limited move tables (white_probas_max.txt, black_probas_max.txt)
+  solve      exact retrograde table of canonical positions (white_solved.txt)
+             with --format binary, the tables are written as .bin files
+  query      best move for --root from a move table
+  verify     check the moves and exact values of a table against the rules
+  perft      move path counts from --root
+  play       play a game in the terminal
+  help       print this message
+
+Options:
+  --depth <plies>         search depth (generate: 15, perft: 4, play: 8)
+  --root <position>       start position, e.g. \"GLE/1C1/1c1/elg w -\"
+  --side <side>           generate tables to write: white, black or both (default both),
+                          for verify the side to move in a generate text table
+                          (needed for those),
+                          for play the side of the human against the engine
+                          (default both: two humans)
+  --white-output <path>   table for player 1
+  --black-output <path>   table for player 2
+  --format <format>       table format, text or binary (default text)
+  --table <path>          table read by query and verify (default: the side's output),
+                          and by the play engine before searching
+  --eval <name>           leaf evaluation for generate (default neutral)
+  --threads <count>       threads used by generate (default: one per core)
+  --checkpoint <dir>      save each depth generate completes in <dir>
+  --resume                continue the generate run checkpointed in --checkpoint
+  --external <dir>        keep the generate frontier and probabilities in sorted
+                          files in <dir>
+  --memory <MiB>          memory for an --external run (default 1024)
+  --stats <path>          JSON summary of a generate or solve run
+                          (default generate_stats.json, solve_stats.json)
+  --log-level <level>     off, error, warn, info, debug or trace (default info)";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Command {
+    Generate,
+    Solve,
+    Query,
+    Verify,
+    Perft,
+    Play,
+    Help,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Side {
+    White,
+    Black,
+    Both,
+}
+
+impl Side {
+    pub fn includes(self, is_player_1: bool) -> bool {
+        match self {
+            Side::White => is_player_1,
+            Side::Black => !is_player_1,
+            Side::Both => true,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Binary,
+}
+
+#[derive(Debug)]
+pub struct CliError(pub String);
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+#[derive(Debug)]
+pub struct Options {
+    pub command: Command,
+    pub depth: Option<u8>,
+    pub root: GameState,
+    pub side: Side,
+    pub white_output: Option<PathBuf>,
+    pub black_output: Option<PathBuf>,
+    pub table: Option<PathBuf>,
+    pub format: Format,
+    pub eval: String,
+    pub threads: Option<usize>,
+    pub checkpoint: Option<PathBuf>,
+    pub resume: bool,
+    pub external: Option<PathBuf>,
+    pub memory: usize,
+    pub stats: Option<PathBuf>,
+    pub log_level: LevelFilter,
+}
+
+fn parse_command(name: &str) -> Result<Command, CliError> {
+    match name {
+        "generate" => Ok(Command::Generate),
+        "solve" => Ok(Command::Solve),
+        "query" => Ok(Command::Query),
+        "verify" => Ok(Command::Verify),
+        "perft" => Ok(Command::Perft),
+        "play" => Ok(Command::Play),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        _ => Err(CliError(format!("unknown command `{}`", name))),
+    }
+}
+
+fn parse_side(value: &str) -> Result<Side, CliError> {
+    match value {
+        "white" | "w" => Ok(Side::White),
+        "black" | "b" => Ok(Side::Black),
+        "both" => Ok(Side::Both),
+        _ => Err(CliError(format!("invalid side `{}`", value))),
+    }
+}
+
+impl Options {
+    // Reads the arguments following the program name. Flags take their value
+    // either as the next argument or after `=`.
+    pub fn parse(args: &[String]) -> Result<Options, CliError> {
+        let mut args = args.iter();
+        let command = match args.next() {
+            Some(name) => parse_command(name)?,
+            None => return Err(CliError("missing command".to_string())),
+        };
+        let mut options = Options {
+            command,
+            depth: None,
+            root: GameState::init(),
+            side: Side::Both,
+            white_output: None,
+            black_output: None,
+            table: None,
+            format: Format::Text,
+            eval: "neutral".to_string(),
+            threads: None,
+            checkpoint: None,
+            resume: false,
+            external: None,
+            memory: 1024,
+            stats: None,
+            log_level: LevelFilter::Info,
+        };
+
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                options.command = Command::Help;
+                continue;
+            }
+            if arg == "--resume" {
+                options.resume = true;
+                continue;
+            }
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if !flag.starts_with("--") {
+                return Err(CliError(format!("unexpected argument `{}`", arg)));
+            }
+            let value = match inline.or_else(|| args.next().cloned()) {
+                Some(value) => value,
+                None => return Err(CliError(format!("missing value for {}", flag))),
+            };
+            let invalid = |what: &str| CliError(format!("invalid {} `{}`", what, value));
+            match flag {
+                "--depth" => options.depth = Some(value.parse().map_err(|_| invalid("depth"))?),
+                "--root" => options.root = value.parse().map_err(|_| invalid("position"))?,
+                "--side" => options.side = parse_side(&value)?,
+                "--white-output" => options.white_output = Some(PathBuf::from(value)),
+                "--black-output" => options.black_output = Some(PathBuf::from(value)),
+                "--table" => options.table = Some(PathBuf::from(value)),
+                "--format" => {
+                    options.format = match value.as_str() {
+                        "text" => Format::Text,
+                        "binary" => Format::Binary,
+                        _ => return Err(invalid("format")),
+                    }
+                }
+                "--eval" => {
+                    if eval::by_name(&value).is_none() {
+                        return Err(CliError(format!(
+                            "unknown evaluator `{}`, expected one of {}",
+                            value,
+                            eval::EVALUATORS.join(", ")
+                        )));
+                    }
+                    options.eval = value
+                }
+                "--threads" => match value.parse() {
+                    Ok(threads) if threads > 0 => options.threads = Some(threads),
+                    _ => return Err(invalid("thread count")),
+                },
+                "--checkpoint" => options.checkpoint = Some(PathBuf::from(value)),
+                "--external" => options.external = Some(PathBuf::from(value)),
+                "--memory" => match value.parse() {
+                    Ok(memory) if memory > 0 => options.memory = memory,
+                    _ => return Err(invalid("memory")),
+                },
+                "--stats" => options.stats = Some(PathBuf::from(value)),
+                "--log-level" => {
+                    options.log_level = value.parse().map_err(|_| invalid("log level"))?
+                }
+                _ => return Err(CliError(format!("unknown option {}", flag))),
+            }
+        }
+        if options.resume && options.checkpoint.is_none() {
+            return Err(CliError("--resume needs --checkpoint".to_string()));
+        }
+        Ok(options)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| {
+            thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
+
+    // Summary of the run, `--stats` if given, otherwise `name`.json.
+    pub fn stats_output(&self, name: &str) -> PathBuf {
+        self.stats
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(name).with_extension("json"))
+    }
+
+    // Table of `is_player_1`, `--white-output` or `--black-output` if given,
+    // otherwise `name` with the extension of `--format`.
+    pub fn output(&self, is_player_1: bool, name: &str) -> PathBuf {
+        let output = if is_player_1 {
+            &self.white_output
+        } else {
+            &self.black_output
+        };
+        output.clone().unwrap_or_else(|| {
+            PathBuf::from(name).with_extension(match self.format {
+                Format::Text => "txt",
+                Format::Binary => "bin",
+            })
+        })
+    }
+}
@@ -0,0 +1,686 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use log::{error, info};
+use memmap2::Mmap;
+
+use game_helper_v2::board::Board;
+use game_helper_v2::eval::Evaluator;
+use game_helper_v2::next_move::NextMove;
+use game_helper_v2::progress::Progress;
+use game_helper_v2::structs::GameResult;
+use game_helper_v2::table::{
+    TableEntry, TableHeader, TableValue, TableWriter, FLAG_MIRROR, SIDE_PLAYER_1, SIDE_PLAYER_2,
+};
+
+use crate::cli::Format;
+use crate::{expand_boards, leaf_proba, side_at};
+
+/// The positions of each depth of a `generate` run, as `calc_proba` walks
+/// them.
+pub trait Levels {
+    // Calls `f` with every position of `depth` and its children.
+    fn for_each(&self, depth: u8, f: &mut dyn FnMut(&Board, &GameResult)) -> io::Result<()>;
+
+    fn for_each_board(&self, depth: u8, f: &mut dyn FnMut(&Board)) -> io::Result<()>;
+}
+
+impl Levels for HashMap<u8, HashMap<Board, GameResult>> {
+    fn for_each(&self, depth: u8, f: &mut dyn FnMut(&Board, &GameResult)) -> io::Result<()> {
+        for (board, game_result) in self[&depth].iter() {
+            f(board, game_result);
+        }
+        Ok(())
+    }
+
+    fn for_each_board(&self, depth: u8, f: &mut dyn FnMut(&Board)) -> io::Result<()> {
+        self[&depth].keys().for_each(f);
+        Ok(())
+    }
+}
+
+// Disk-backed frontier for `generate --external`. Each depth is a file of
+// sorted, distinct boards (`level_NN.bin`, u64 little-endian) and children
+// are never kept: they are recomputed from the boards when needed.
+//
+// A depth is built from the one before it in chunks: the chunk's children
+// are gathered in a buffer, which is sorted and written as a run when it is
+// full. The runs are then merged, dropping duplicates and the boards of the
+// earlier depths with the same side to move, which are read alongside since
+// they are sorted too. Chunks and buffers are sized from the memory budget,
+// and so is the number of runs merged at once: when there are more, they are
+// merged in several passes.
+//
+// Back-propagation goes one depth at a time too. A depth's boards are read in
+// order, their children looked up in the probabilities of the depth after it,
+// which are searched in place in their file, and the depth's probabilities
+// written in the same order as a file of `PROBA_SIZE` records: board, then
+// the probability bits as a u32, then the move. A depth's probabilities are
+// removed once the depth before it is done, unless they go in the table.
+
+const READ_BUFFER: usize = 1 << 16;
+// runs merged at once, whatever the budget, to stay clear of the limit on
+// open files
+const MAX_FAN_IN: usize = 256;
+const PROBA_SIZE: usize = 14;
+// rough size of a board expanded with its children
+pub const EXPANDED_SIZE: usize = 512;
+
+struct BoardReader {
+    reader: BufReader<File>,
+}
+
+impl BoardReader {
+    fn open(path: &Path) -> io::Result<BoardReader> {
+        Ok(BoardReader {
+            reader: BufReader::with_capacity(READ_BUFFER, File::open(path)?),
+        })
+    }
+
+    fn next_board(&mut self) -> io::Result<Option<u64>> {
+        let mut bytes = [0u8; 8];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Up to `n` boards.
+    fn next_chunk(&mut self, n: usize) -> io::Result<Vec<Board>> {
+        let mut chunk = Vec::with_capacity(n);
+        while chunk.len() < n {
+            match self.next_board()? {
+                Some(board) => chunk.push(Board(board)),
+                None => break,
+            }
+        }
+        Ok(chunk)
+    }
+}
+
+type Proba = (u64, f32, NextMove);
+
+fn proba_to_bytes((board, proba, next_move): Proba) -> [u8; PROBA_SIZE] {
+    let mut bytes = [0u8; PROBA_SIZE];
+    bytes[0..8].copy_from_slice(&board.to_le_bytes());
+    bytes[8..12].copy_from_slice(&proba.to_bits().to_le_bytes());
+    bytes[12..14].copy_from_slice(&next_move.0.to_le_bytes());
+    bytes
+}
+
+fn proba_from_bytes(bytes: &[u8]) -> Proba {
+    let mut board = [0u8; 8];
+    board.copy_from_slice(&bytes[0..8]);
+    let proba = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+    (
+        u64::from_le_bytes(board),
+        f32::from_bits(proba),
+        NextMove(u16::from_le_bytes([bytes[12], bytes[13]])),
+    )
+}
+
+// The probabilities of a depth read in order.
+struct ProbaReader {
+    reader: BufReader<File>,
+}
+
+impl ProbaReader {
+    fn open(path: &Path) -> io::Result<ProbaReader> {
+        Ok(ProbaReader {
+            reader: BufReader::with_capacity(READ_BUFFER, File::open(path)?),
+        })
+    }
+
+    fn next_proba(&mut self) -> io::Result<Option<Proba>> {
+        let mut bytes = [0u8; PROBA_SIZE];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Ok(Some(proba_from_bytes(&bytes))),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// The probabilities of a depth mapped in memory, searched by board. Only the
+// pages the searches touch are read, and they can be dropped again.
+struct ProbaLevel {
+    // `None` for an empty file, which cannot be mapped
+    mmap: Option<Mmap>,
+}
+
+impl ProbaLevel {
+    fn open(path: &Path) -> io::Result<ProbaLevel> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(ProbaLevel { mmap: None });
+        }
+        // the file is not changed while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(ProbaLevel { mmap: Some(mmap) })
+    }
+
+    fn get(&self, board: u64) -> Option<f32> {
+        let mmap = self.mmap.as_ref()?;
+        let record = |i: usize| proba_from_bytes(&mmap[i * PROBA_SIZE..(i + 1) * PROBA_SIZE]);
+        let (mut lo, mut hi) = (0, mmap.len() / PROBA_SIZE);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let (key, proba, _) = record(mid);
+            match key.cmp(&board) {
+                std::cmp::Ordering::Equal => return Some(proba),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+            }
+        }
+        None
+    }
+}
+
+fn write_boards(path: &Path, boards: &[u64]) -> io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    for board in boards {
+        f.write_all(&board.to_le_bytes())?;
+    }
+    f.flush()
+}
+
+pub struct ExternalFrontier {
+    dir: PathBuf,
+    memory: usize,
+    threads: usize,
+    root_is_player_1: bool,
+}
+
+impl ExternalFrontier {
+    // `memory` is the budget in bytes for the boards held at once.
+    pub fn new(
+        dir: &Path,
+        memory: usize,
+        threads: usize,
+        root_is_player_1: bool,
+    ) -> io::Result<ExternalFrontier> {
+        fs::create_dir_all(dir)?;
+        Ok(ExternalFrontier {
+            dir: dir.to_path_buf(),
+            memory,
+            threads,
+            root_is_player_1,
+        })
+    }
+
+    fn level_path(&self, depth: u8) -> PathBuf {
+        self.dir.join(format!("level_{:02}.bin", depth))
+    }
+
+    fn chunk_size(&self) -> usize {
+        (self.memory / 4 / EXPANDED_SIZE).max(1)
+    }
+
+    fn run_size(&self) -> usize {
+        (self.memory / 2 / 8).max(1)
+    }
+
+    // Files read at once by a merge, each through a `READ_BUFFER`.
+    fn fan_in(&self) -> usize {
+        (self.memory / READ_BUFFER).clamp(2, MAX_FAN_IN)
+    }
+
+    fn proba_path(&self, is_player_one: bool, depth: u8) -> PathBuf {
+        let side = if is_player_one { "white" } else { "black" };
+        self.dir.join(format!("{}_{:02}.bin", side, depth))
+    }
+
+    // Builds depths 1 to `max_depth` from the canonical `root_board`. Each
+    // depth is a stage of `progress`, counted while it is expanded, so the
+    // outcomes of the last one are not known.
+    pub fn generate(
+        &self,
+        root_board: Board,
+        max_depth: u8,
+        progress: &mut Progress,
+    ) -> io::Result<()> {
+        write_boards(&self.level_path(0), &[root_board.0])?;
+        for depth in 0..max_depth {
+            info!("Depth {}", depth);
+            progress.start("generate", depth as u32, 0);
+            let count = self.expand_level(depth, progress)?;
+            if count == 0 {
+                error!("FINAL DEPTH : {}", depth)
+            }
+            if depth + 1 == max_depth {
+                progress.start("generate", max_depth as u32, 0);
+                progress.add(count);
+            }
+        }
+        progress.finish();
+        Ok(())
+    }
+
+    // Writes the children of depth `depth` as depth `depth + 1`, returning
+    // how many there are.
+    fn expand_level(&self, depth: u8, progress: &mut Progress) -> io::Result<u64> {
+        let is_player_one = side_at(self.root_is_player_1, depth);
+        let mut runs = vec![];
+        let mut buffer: Vec<u64> = Vec::with_capacity(self.run_size());
+        let mut reader = BoardReader::open(&self.level_path(depth))?;
+
+        loop {
+            let chunk = reader.next_chunk(self.chunk_size())?;
+            if chunk.is_empty() {
+                break;
+            }
+            for (children, outcomes) in self.children(&chunk, is_player_one) {
+                progress.add_outcomes(outcomes[0], outcomes[1], outcomes[2]);
+                for child in children {
+                    buffer.push(child);
+                    if buffer.len() == self.run_size() {
+                        self.spill(depth + 1, &mut buffer, &mut runs)?;
+                    }
+                }
+            }
+        }
+        if !buffer.is_empty() || runs.is_empty() {
+            self.spill(depth + 1, &mut buffer, &mut runs)?;
+        }
+        drop(buffer);
+
+        // boards already expanded for the side to move next, as in memory
+        let seen: Vec<PathBuf> = (0..depth.saturating_sub(1))
+            .filter(|d| side_at(self.root_is_player_1, *d) != is_player_one)
+            .map(|d| self.level_path(d))
+            .collect();
+        // the last merge reads the `seen` levels as well
+        let fan_in = self.fan_in().saturating_sub(seen.len()).max(2);
+        let mut pass = 0;
+        while runs.len() > fan_in {
+            pass += 1;
+            let mut merged = vec![];
+            for group in runs.chunks(fan_in) {
+                let run = self.dir.join(format!(
+                    "run_{:02}_{}_{}.bin",
+                    depth + 1,
+                    pass,
+                    merged.len()
+                ));
+                merge(group, &[], &run)?;
+                for run in group {
+                    fs::remove_file(run)?;
+                }
+                merged.push(run);
+            }
+            runs = merged;
+        }
+        let count = merge(&runs, &seen, &self.level_path(depth + 1))?;
+        for run in runs {
+            fs::remove_file(run)?;
+        }
+        Ok(count)
+    }
+
+    // Back-propagates the chances of `is_player_one` winning from depth
+    // `max_depth - 1`, evaluated by `evaluator`, to the root, as `calc_proba`
+    // does in memory. Each depth is a stage of `progress`.
+    fn back_propagate(
+        &self,
+        is_player_one: bool,
+        max_depth: u8,
+        evaluator: &dyn Evaluator,
+        progress: &mut Progress,
+    ) -> io::Result<()> {
+        let phase = if is_player_one { "white" } else { "black" };
+        let root_is_player_1 = self.root_is_player_1;
+        for depth in (0..max_depth).rev() {
+            info!("{}", depth);
+            let is_our_turn = side_at(root_is_player_1, depth) == is_player_one;
+            let next = if depth + 1 < max_depth {
+                Some(ProbaLevel::open(
+                    &self.proba_path(is_player_one, depth + 1),
+                )?)
+            } else {
+                None
+            };
+            progress.start(phase, depth as u32, 0);
+            let mut f = BufWriter::new(File::create(self.proba_path(is_player_one, depth))?);
+            let mut reader = BoardReader::open(&self.level_path(depth))?;
+            loop {
+                let chunk = reader.next_chunk(self.chunk_size())?;
+                if chunk.is_empty() {
+                    break;
+                }
+                let expanded =
+                    expand_boards(&chunk, side_at(root_is_player_1, depth), self.threads);
+                for board in chunk.iter() {
+                    let (proba, next_move) = match (&expanded[board], &next) {
+                        (GameResult::WhiteWin, _) => (is_player_one as u8 as f32, NextMove(0)),
+                        (GameResult::BlackWin, _) => (!is_player_one as u8 as f32, NextMove(0)),
+                        // the last depth is evaluated
+                        (GameResult::Intermediate(_), None) => (
+                            leaf_proba(evaluator, *board, root_is_player_1, depth, is_player_one),
+                            NextMove(0),
+                        ),
+                        (GameResult::Intermediate(children), Some(next)) => {
+                            let probas = children.iter().map(|(next_move, child)| {
+                                let proba = next.get(child.canonical().0 .0).unwrap_or_else(|| {
+                                    leaf_proba(
+                                        evaluator,
+                                        *child,
+                                        root_is_player_1,
+                                        depth + 1,
+                                        is_player_one,
+                                    )
+                                });
+                                (proba, *next_move)
+                            });
+                            if is_our_turn {
+                                // the first of the best moves
+                                probas.fold((-2f32, NextMove(0)), |best, (proba, next_move)| {
+                                    if proba > best.0 {
+                                        (proba, next_move)
+                                    } else {
+                                        best
+                                    }
+                                })
+                            } else {
+                                let (sum, count) = probas
+                                    .fold((0f32, 0f32), |(sum, count), (proba, _)| {
+                                        (sum + proba, count + 1f32)
+                                    });
+                                (sum / count, NextMove(0))
+                            }
+                        }
+                    };
+                    f.write_all(&proba_to_bytes((board.0, proba, next_move)))?;
+                }
+                progress.add(chunk.len() as u64);
+            }
+            f.flush()?;
+            drop(next);
+            // the depth after this one is only kept for the table
+            if depth + 1 < max_depth && side_at(root_is_player_1, depth + 1) != is_player_one {
+                fs::remove_file(self.proba_path(is_player_one, depth + 1))?;
+            }
+        }
+        progress.finish();
+        Ok(())
+    }
+
+    // Back-propagates the positions for `is_player_one`, as `back_propagate`
+    // does, and writes its table to `output`: the moves of the depths where
+    // it is to move, merged in board order.
+    pub fn write_probas(
+        &self,
+        is_player_one: bool,
+        max_depth: u8,
+        evaluator: &dyn Evaluator,
+        output: &Path,
+        format: Format,
+        progress: &mut Progress,
+    ) -> io::Result<()> {
+        self.back_propagate(is_player_one, max_depth, evaluator, progress)?;
+        let ours: Vec<PathBuf> = (0..max_depth)
+            .filter(|depth| side_at(self.root_is_player_1, *depth) == is_player_one)
+            .map(|depth| self.proba_path(is_player_one, depth))
+            .collect();
+        let mut readers = ours
+            .iter()
+            .map(|path| ProbaReader::open(path))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut heap = BinaryHeap::new();
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if let Some((board, proba, next_move)) = reader.next_proba()? {
+                heap.push(Reverse((board, i, proba.to_bits(), next_move.0)));
+            }
+        }
+
+        let mut text = None;
+        let mut binary = None;
+        match format {
+            Format::Text => text = Some(BufWriter::new(File::create(output)?)),
+            Format::Binary => {
+                let side = if is_player_one {
+                    SIDE_PLAYER_1
+                } else {
+                    SIDE_PLAYER_2
+                };
+                let header = TableHeader::new(max_depth, side, FLAG_MIRROR);
+                binary = Some(TableWriter::create(output, header)?);
+            }
+        }
+        let mut last = None;
+        while let Some(Reverse((board, i, proba, next_move))) = heap.pop() {
+            if let Some((board, proba, next_move)) = readers[i].next_proba()? {
+                heap.push(Reverse((board, i, proba.to_bits(), next_move.0)));
+            }
+            if next_move == 0 || last == Some(board) {
+                continue;
+            }
+            last = Some(board);
+            if let Some(f) = text.as_mut() {
+                f.write_all(format!("{:X} {:X}\n", board, next_move).as_bytes())?;
+            }
+            if let Some(writer) = binary.as_mut() {
+                writer.push(TableEntry {
+                    board: Board(board),
+                    next_move: NextMove(next_move),
+                    value: TableValue::Probability(f32::from_bits(proba)),
+                })?;
+            }
+        }
+        if let Some(mut f) = text {
+            f.flush()?;
+        }
+        if let Some(writer) = binary {
+            writer.finish()?;
+        }
+        drop(readers);
+        for path in ours {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // Writes `buffer` as a sorted run of depth `depth`.
+    fn spill(&self, depth: u8, buffer: &mut Vec<u64>, runs: &mut Vec<PathBuf>) -> io::Result<()> {
+        buffer.sort_unstable();
+        buffer.dedup();
+        let run = self
+            .dir
+            .join(format!("run_{:02}_{}.bin", depth, runs.len()));
+        write_boards(&run, buffer)?;
+        runs.push(run);
+        buffer.clear();
+        Ok(())
+    }
+
+    // Canonical children of the non-final boards of `chunk`, on `threads`
+    // threads, with the number of white wins, black wins and intermediate
+    // boards.
+    fn children(&self, chunk: &[Board], is_player_one: bool) -> Vec<(Vec<u64>, [u64; 3])> {
+        let chunk_size = chunk.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            let workers: Vec<_> = chunk
+                .chunks(chunk_size)
+                .map(|part| {
+                    scope.spawn(move || {
+                        let mut children = vec![];
+                        let mut outcomes = [0; 3];
+                        for board in part {
+                            match board.get_next_states_2(is_player_one) {
+                                GameResult::WhiteWin => outcomes[0] += 1,
+                                GameResult::BlackWin => outcomes[1] += 1,
+                                GameResult::Intermediate(boards) => {
+                                    outcomes[2] += 1;
+                                    children.extend(boards.iter().map(|(_, b)| b.canonical().0 .0));
+                                }
+                            }
+                        }
+                        (children, outcomes)
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        })
+    }
+}
+
+// Merges the sorted `runs` into `output` without duplicates or the boards of
+// the sorted files `seen`, returning the number of boards written.
+fn merge(runs: &[PathBuf], seen: &[PathBuf], output: &Path) -> io::Result<u64> {
+    let mut readers = runs
+        .iter()
+        .map(|run| BoardReader::open(run))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::new();
+    for (i, reader) in readers.iter_mut().enumerate() {
+        if let Some(board) = reader.next_board()? {
+            heap.push(Reverse((board, i)));
+        }
+    }
+    let mut seen_readers = vec![];
+    for path in seen {
+        let mut reader = BoardReader::open(path)?;
+        let head = reader.next_board()?;
+        seen_readers.push((reader, head));
+    }
+
+    let mut f = BufWriter::new(File::create(output)?);
+    let mut last = None;
+    let mut count = 0;
+    while let Some(Reverse((board, i))) = heap.pop() {
+        if let Some(next) = readers[i].next_board()? {
+            heap.push(Reverse((next, i)));
+        }
+        if last == Some(board) {
+            continue;
+        }
+        last = Some(board);
+
+        let mut is_seen = false;
+        for (reader, head) in seen_readers.iter_mut() {
+            while head.is_some_and(|h| h < board) {
+                *head = reader.next_board()?;
+            }
+            is_seen |= *head == Some(board);
+        }
+        if !is_seen {
+            f.write_all(&board.to_le_bytes())?;
+            count += 1;
+        }
+    }
+    f.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{calc_proba, expand_frontier};
+    use game_helper_v2::eval;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("frontier-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // The depths `generate` builds in memory, from the initial position.
+    fn memory_levels(max_depth: u8) -> HashMap<u8, HashMap<Board, GameResult>> {
+        let root = Board::init().canonical().0;
+        let mut levels = HashMap::new();
+        levels.insert(0, HashMap::from([(root, root.get_next_states_2(true))]));
+        for depth in 0..max_depth {
+            let is_player_one = side_at(true, depth);
+            let seen: Vec<&HashMap<Board, GameResult>> = (0..depth.saturating_sub(1))
+                .filter(|d| side_at(true, *d) != is_player_one)
+                .map(|d| &levels[&d])
+                .collect();
+            let next = expand_frontier(&levels[&depth], &seen, !is_player_one, 2);
+            levels.insert(depth + 1, next);
+        }
+        levels
+    }
+
+    fn read_level(frontier: &ExternalFrontier, depth: u8) -> Vec<u64> {
+        let mut reader = BoardReader::open(&frontier.level_path(depth)).unwrap();
+        let mut boards = vec![];
+        while let Some(board) = reader.next_board().unwrap() {
+            boards.push(board);
+        }
+        boards
+    }
+
+    #[test]
+    fn merges_more_runs_than_the_fan_in() {
+        let max_depth = 7;
+        let dir = temp_dir("fan-in");
+        // two runs merged at once, of 4096 boards each
+        let frontier = ExternalFrontier::new(&dir, READ_BUFFER, 2, true).unwrap();
+        assert_eq!(frontier.fan_in(), 2);
+        frontier
+            .generate(
+                Board::init().canonical().0,
+                max_depth,
+                &mut Progress::new("test"),
+            )
+            .unwrap();
+
+        let levels = memory_levels(max_depth);
+        for depth in 0..=max_depth {
+            let mut expected: Vec<u64> = levels[&depth].keys().map(|board| board.0).collect();
+            expected.sort_unstable();
+            assert_eq!(read_level(&frontier, depth), expected, "depth {}", depth);
+        }
+        // the last depth was spilled in more runs than the fan-in
+        assert!(levels[&max_depth].len() > 3 * frontier.run_size());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn back_propagates_as_in_memory() {
+        let max_depth = 5;
+        let dir = temp_dir("probas");
+        let frontier = ExternalFrontier::new(&dir, READ_BUFFER, 2, true).unwrap();
+        let mut progress = Progress::new("test");
+        frontier
+            .generate(Board::init().canonical().0, max_depth, &mut progress)
+            .unwrap();
+        let levels = memory_levels(max_depth);
+        let evaluator = eval::by_name("material").unwrap();
+
+        for is_player_one in [true, false] {
+            for format in [Format::Text, Format::Binary] {
+                let external = dir.join("external");
+                let memory = dir.join("memory");
+                frontier
+                    .write_probas(
+                        is_player_one,
+                        max_depth,
+                        evaluator.as_ref(),
+                        &external,
+                        format,
+                        &mut progress,
+                    )
+                    .unwrap();
+                let probas = calc_proba(
+                    is_player_one,
+                    true,
+                    max_depth,
+                    &levels,
+                    evaluator.as_ref(),
+                    None,
+                    &mut progress,
+                )
+                .unwrap();
+                crate::write_probas(is_player_one, max_depth, &probas, &memory, format).unwrap();
+                assert_eq!(fs::read(&external).unwrap(), fs::read(&memory).unwrap());
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,49 +1,85 @@
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
 use std::fmt::format;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
 use std::sync::{Arc, RwLock};
-use std::{env, mem, vec};
+use std::{env, mem, thread, vec};
 
 use log::{debug, error, info};
 
 use game_helper_v2;
 use game_helper_v2::board::Board;
+use game_helper_v2::eval::{self, Evaluator};
+use game_helper_v2::game_state::GameState;
 use game_helper_v2::next_move::NextMove;
-use game_helper_v2::piece::{
-    CHICK_1, CHICK_2, ELEPHANT_1, ELEPHANT_2, GIRAFFE_1, GIRAFFE_2, LION_1, LION_2,
-};
+use game_helper_v2::perft::{perft, perft_divide};
+use game_helper_v2::progress::Progress;
+use game_helper_v2::retrograde;
 use game_helper_v2::structs::Calc::Proba;
-use game_helper_v2::structs::Position::{
-    Dead, X0Y0, X0Y1, X0Y2, X0Y3, X1Y0, X1Y1, X1Y2, X1Y3, X2Y0, X2Y1, X2Y3,
-};
 use game_helper_v2::structs::{Calc, GameResult};
+use game_helper_v2::table::{
+    write_table, TableEntry, TableHeader, TableValue, DEPTH_EXACT, FLAG_COLOUR_FLIP, FLAG_MIRROR,
+    SIDE_PLAYER_1, SIDE_PLAYER_2,
+};
+use game_helper_v2::tablebase::Tablebase;
+use game_helper_v2::verify::verify;
+
+use checkpoint::{Checkpoint, RunParams};
+use cli::{CliError, Command, Format, Options, Side, USAGE};
+use frontier::{ExternalFrontier, Levels, EXPANDED_SIZE};
+
+mod checkpoint;
+mod cli;
+mod frontier;
+mod play_game;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) if args.is_empty() => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            eprintln!("error: {}\nRun `dobustu-precomp help` for usage.", e);
+            return ExitCode::from(2);
+        }
+    };
+    pretty_env_logger::formatted_builder()
+        .filter_level(options.log_level)
+        .init();
 
-fn main() {
-    env::set_var("RUST_LOG", "debug");
-    pretty_env_logger::init();
-
-    let mut board_test = Board::new_empty();
-    let state = [
-        (LION_1, X1Y0),
-        (LION_2, X1Y3),
-        (ELEPHANT_1, X0Y0),
-        (ELEPHANT_2, X2Y3),
-        (GIRAFFE_1, X2Y0),
-        (GIRAFFE_2, X0Y3),
-        (CHICK_1, X1Y1),
-        (CHICK_2, X1Y2),
-    ];
-    board_test.put_state(state);
-    info!("board {:X}", board_test.0);
-    board_test.debug_show_board_2();
-
-    sequential_comp()
-
-    // complete_black_comp(&mut file);
-    // complete_white_comp(&mut file2);
-    //bench();
-    //calc();
+    let result = match options.command {
+        Command::Generate => sequential_comp(&options),
+        Command::Solve => retrograde_comp(&options),
+        Command::Query => query(&options),
+        Command::Verify => verify_table(&options),
+        Command::Perft => {
+            perft_comp(options.root, options.depth.unwrap_or(4));
+            Ok(())
+        }
+        Command::Play => play_game::play(&options),
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // options that only turn out to be missing once a file is read
+        Err(e) if e.is::<CliError>() => {
+            eprintln!("error: {}\nRun `dobustu-precomp help` for usage.", e);
+            ExitCode::from(2)
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
 
 // fn complete_black_comp(file: &mut File) {
@@ -90,68 +126,425 @@ fn main() {
 //     info!("Proba: {}", proba);
 // }
 
-const MAX_DEPTH: u8 = 15;
+const DEFAULT_DEPTH: u8 = 15;
 
-fn sequential_comp() {
-    let mut calc_state: HashMap<u8, HashMap<Board, GameResult>> = HashMap::default();
+fn retrograde_comp(options: &Options) -> Result<(), Box<dyn Error>> {
+    let root = options.root;
+    let mut progress = Progress::new("solve");
+    progress.note("root", &root.to_string());
+    let table = retrograde::solve(root, &mut progress);
+    info!("Root value: {}", table.value(&root).unwrap());
+    write_solved(options, &table)?;
+    progress.write_summary(options.stats_output("solve_stats"))?;
+    Ok(())
+}
 
-    let mut is_player_one = true;
+fn write_solved(options: &Options, table: &retrograde::RetrogradeTable) -> io::Result<()> {
+    // the table only holds canonical positions, which all have player 1 to
+    // move
+    let output = options.output(true, "white_solved");
+    if options.format == Format::Binary {
+        let entries = table
+            .iter()
+            .map(|(state, value)| TableEntry {
+                board: state.board,
+                next_move: table.best_move(&state).unwrap_or(NextMove(0)),
+                value: TableValue::Exact(value),
+            })
+            .collect();
+        let header = TableHeader::new(DEPTH_EXACT, SIDE_PLAYER_1, FLAG_MIRROR | FLAG_COLOUR_FLIP);
+        return write_table(output, header, entries);
+    }
+    let mut f = BufWriter::new(File::create(output)?);
+    for (state, value) in table.iter() {
+        let next = table.best_move(&state).unwrap_or(NextMove(0));
+        f.write_all(format!("{:X} {:X} {}\n", state.board.0, next, value).as_bytes())?;
+    }
+    f.flush()
+}
+
+// Looks the root position up in a table written by `generate` or `solve`.
+fn query(options: &Options) -> Result<(), Box<dyn Error>> {
+    let root = options.root;
+    let table = match &options.table {
+        Some(table) => table.clone(),
+        None => options.output(
+            root.is_player_1,
+            if root.is_player_1 {
+                "white_probas_max"
+            } else {
+                "black_probas_max"
+            },
+        ),
+    };
+    let tablebase = Tablebase::open(&table).map_err(|e| format!("{}: {}", table.display(), e))?;
+    if !tablebase.contains(&root.board, root.is_player_1) {
+        return Err(format!("{} is not in {}", root, table.display()).into());
+    }
+    let value = tablebase.probe(&root.board, root.is_player_1);
+    match (tablebase.best_move(&root.board, root.is_player_1), value) {
+        (Some(next), Some(value)) => println!("{} {}", next.notation(&root.board), value),
+        (Some(next), None) => println!("{}", next.notation(&root.board)),
+        (None, Some(value)) => println!("no move ({})", value),
+        (None, None) => println!("no move (game over)"),
+    }
+    Ok(())
+}
+
+// Issues printed in full by `verify`, the rest are only counted.
+const MAX_REPORTED: usize = 20;
 
-    let mut hs = HashMap::new();
-    hs.insert(
-        Board::init(),
-        Board::init().get_next_states_2(is_player_one),
+// Checks the moves and values of a table, see `verify::verify`.
+fn verify_table(options: &Options) -> Result<(), Box<dyn Error>> {
+    let is_player_1 = options.side != Side::Black;
+    let table = match &options.table {
+        Some(table) => table.clone(),
+        None => options.output(
+            is_player_1,
+            if is_player_1 {
+                "white_probas_max"
+            } else {
+                "black_probas_max"
+            },
+        ),
+    };
+    let tablebase = Tablebase::open(&table).map_err(|e| format!("{}: {}", table.display(), e))?;
+    // the positions of `generate`'s text tables have the side to move the
+    // table was generated for, which only the caller knows
+    let is_player_1 = match (tablebase.side(), options.side) {
+        (None, Side::Both) => {
+            return Err(CliError(format!(
+                "{} does not say its side to move, give --side white or --side black",
+                table.display()
+            ))
+            .into())
+        }
+        (Some(side), Side::White | Side::Black) if side != is_player_1 => {
+            return Err(CliError(format!(
+                "{} holds positions with {} to move",
+                table.display(),
+                if side { "white" } else { "black" }
+            ))
+            .into())
+        }
+        (side, _) => side.unwrap_or(is_player_1),
+    };
+    let report = verify(&tablebase, is_player_1);
+    for issue in report.issues.iter().take(MAX_REPORTED) {
+        println!("{}\n", issue);
+    }
+    if report.issues.len() > MAX_REPORTED {
+        println!("... and {} more", report.issues.len() - MAX_REPORTED);
+    }
+    println!(
+        "{}: {} entries checked, {} issues",
+        table.display(),
+        report.checked,
+        report.issues.len()
     );
-    calc_state.insert(0, hs);
+    if report.moves_only > 0 {
+        println!(
+            "{} entries with a probability: only their moves were checked, as their values \
+             depend on the depth each position was reached at and cannot be recomputed from \
+             the table",
+            report.moves_only
+        );
+    }
+    if report.is_ok() {
+        Ok(())
+    } else {
+        Err(format!("{} is inconsistent", table.display()).into())
+    }
+}
 
-    info!("Generating...");
+fn perft_comp(root: GameState, max_depth: u8) {
+    let board = root.board;
+    for depth in 1..=max_depth {
+        let start = std::time::Instant::now();
+        let stats = perft(board, root.is_player_1, depth);
+        info!(
+            "perft({}) {} in {}ms",
+            depth,
+            stats,
+            start.elapsed().as_millis()
+        );
+    }
 
-    for depth in 0u8..MAX_DEPTH {
-        info!("Depth {}", depth);
-        let current_hashmap = calc_state.get(&depth).unwrap();
+    info!("Divide at depth {}", max_depth);
+    let mut total = 0;
+    for (next_move, stats) in perft_divide(board, root.is_player_1, max_depth) {
+        println!("{}: {}", next_move, stats.nodes);
+        total += stats.nodes;
+    }
+    println!("Total: {}", total);
+}
 
-        let mut next_hashmap = HashMap::new();
-        for game_result in current_hashmap.values() {
-            match game_result {
-                GameResult::WhiteWin | GameResult::BlackWin => continue,
-                GameResult::Intermediate(game_result_board_vec) => {
-                    'outer: for (_, board) in game_result_board_vec {
-                        // if board.0 == 0x8C7C696C45302A11 {
-                        //     error!("a");
-                        // }
-                        if depth >= 2 {
-                            for d in 0u8..depth - 1 {
-                                if (is_player_one && d % 2 == 0) || (!is_player_one && d % 2 != 0) {
-                                    continue;
-                                }
-                                if calc_state.get(&(d)).unwrap().contains_key(board) {
-                                    continue 'outer;
-                                }
-                            }
-                        }
-                        // if board.0 == 0x8C7C696C45302A11 {
-                        //     error!("Found");
-                        // }
-                        next_hashmap.insert(*board, board.get_next_states_2(!is_player_one));
-                    }
-                }
+fn sequential_comp(options: &Options) -> Result<(), Box<dyn Error>> {
+    let max_depth = options.depth.unwrap_or(DEFAULT_DEPTH);
+    if max_depth == 0 {
+        return Err("generate needs a depth of at least 1".into());
+    }
+    let evaluator = eval::by_name(&options.eval).unwrap();
+    let threads = options.threads();
+    let root = options.root;
+    let mut calc_state: HashMap<u8, HashMap<Board, GameResult>> = HashMap::default();
+
+    let mut progress = Progress::new("generate");
+    progress.note("root", &root.to_string());
+    progress.note("depth", &max_depth.to_string());
+    progress.note("eval", &options.eval);
+    progress.note("threads", &threads.to_string());
+    progress.expect_depth(max_depth as u32);
+
+    if let Some(dir) = &options.external {
+        if options.checkpoint.is_some() {
+            return Err("--external cannot be combined with --checkpoint".into());
+        }
+        progress.note("frontier", "external");
+        let frontier = ExternalFrontier::new(dir, options.memory << 20, threads, root.is_player_1)?;
+        info!("Generating...");
+        frontier.generate(root.board.canonical().0, max_depth, &mut progress)?;
+        calc_tables(
+            options,
+            max_depth,
+            &mut progress,
+            &mut |is_player_1, output, progress| {
+                frontier.write_probas(
+                    is_player_1,
+                    max_depth,
+                    evaluator.as_ref(),
+                    output,
+                    options.format,
+                    progress,
+                )
+            },
+        )?;
+        progress.write_summary(options.stats_output("generate_stats"))?;
+        return Ok(());
+    }
+    progress.note("frontier", "memory");
+
+    let checkpoint = match &options.checkpoint {
+        Some(dir) => {
+            let params = RunParams {
+                root: root.to_string(),
+                depth: max_depth,
+                eval: options.eval.clone(),
+            };
+            Some(if options.resume {
+                Checkpoint::resume(dir, &params)?
+            } else {
+                Checkpoint::create(dir, &params)?
+            })
+        }
+        None => None,
+    };
+
+    if let Some(checkpoint) = checkpoint.as_ref().filter(|_| options.resume) {
+        for (depth, boards) in checkpoint.load_levels().iter().enumerate() {
+            let depth = depth as u8;
+            if depth > max_depth {
+                break;
             }
+            info!("Restoring depth {}", depth);
+            let is_player_one = side_at(root.is_player_1, depth);
+            progress.start("restore", depth as u32, EXPANDED_SIZE as u64);
+            let expanded = expand_boards(boards, is_player_one, threads);
+            expanded.values().for_each(|r| progress.record(r));
+            calc_state.insert(depth, expanded);
         }
+    }
+
+    // boards are stored as the smaller of their two mirror images, and the
+    // moves in the tables are those of the stored board
+    if calc_state.is_empty() {
+        let (root_board, _) = root.board.canonical();
+        let mut hs = HashMap::new();
+        progress.start("generate", 0, EXPANDED_SIZE as u64);
+        hs.insert(root_board, root_board.get_next_states_2(root.is_player_1));
+        hs.values().for_each(|r| progress.record(r));
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.save_level(0, hs.keys())?;
+        }
+        calc_state.insert(0, hs);
+    }
+
+    info!("Generating...");
+
+    for depth in (calc_state.len() - 1) as u8..max_depth {
+        info!("Depth {}", depth);
+        let is_player_one = side_at(root.is_player_1, depth);
+        let current_hashmap = calc_state.get(&depth).unwrap();
+        // each depth is a stage, timed while it is built and expanded
+        progress.start("generate", (depth + 1) as u32, EXPANDED_SIZE as u64);
+        // positions already expanded for the side to move next
+        let seen: Vec<&HashMap<Board, GameResult>> = (0..depth.saturating_sub(1))
+            .filter(|d| side_at(root.is_player_1, *d) != is_player_one)
+            .map(|d| calc_state.get(&d).unwrap())
+            .collect();
+        let next_hashmap = expand_frontier(current_hashmap, &seen, !is_player_one, threads);
 
         if next_hashmap.is_empty() {
             error!("FINAL DEPTH : {}", depth)
         }
+        next_hashmap.values().for_each(|r| progress.record(r));
 
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.save_level(depth + 1, next_hashmap.keys())?;
+        }
         calc_state.insert(depth + 1, next_hashmap);
-        is_player_one = !is_player_one;
     }
+    progress.finish();
 
     // duplicate_checker(&calc_state);
 
-    info!("Calculating White");
-    calc_proba(true, &calc_state);
-    info!("Calculating Black");
-    calc_proba(false, &calc_state);
+    calc_tables(
+        options,
+        max_depth,
+        &mut progress,
+        &mut |is_player_1, output, progress| {
+            let probas = calc_proba(
+                is_player_1,
+                root.is_player_1,
+                max_depth,
+                &calc_state,
+                evaluator.as_ref(),
+                checkpoint.as_ref(),
+                progress,
+            )?;
+            write_probas(is_player_1, max_depth, &probas, output, options.format)
+        },
+    )?;
+    progress.write_summary(options.stats_output("generate_stats"))?;
+    Ok(())
+}
+
+// Writes the tables of the sides asked for with `write_side`, which
+// back-propagates the positions of one side into a table.
+fn calc_tables(
+    options: &Options,
+    max_depth: u8,
+    progress: &mut Progress,
+    write_side: &mut dyn FnMut(bool, &Path, &mut Progress) -> io::Result<()>,
+) -> Result<(), Box<dyn Error>> {
+    // back-propagation visits the positions before the last depth once per
+    // side
+    let sides = [true, false]
+        .iter()
+        .filter(|is_player_1| options.side.includes(**is_player_1))
+        .count() as u64;
+    let per_side: u64 = progress
+        .stages()
+        .iter()
+        .filter(|stage| stage.phase == "generate" || stage.phase == "restore")
+        .filter(|stage| stage.depth < max_depth as u32)
+        .map(|stage| stage.positions)
+        .sum();
+    progress.expect(progress.positions() + sides * per_side);
+    for is_player_1 in [true, false] {
+        if !options.side.includes(is_player_1) {
+            continue;
+        }
+        let output = if is_player_1 {
+            info!("Calculating White");
+            options.output(true, "white_probas_max")
+        } else {
+            info!("Calculating Black");
+            options.output(false, "black_probas_max")
+        };
+        write_side(is_player_1, &output, progress)?;
+    }
+    Ok(())
+}
+
+// `boards` with `is_player_one` to move, expanded on `threads` threads.
+fn expand_boards(
+    boards: &[Board],
+    is_player_one: bool,
+    threads: usize,
+) -> HashMap<Board, GameResult> {
+    let chunk_size = boards.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = boards
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|board| (*board, board.get_next_states_2(is_player_one)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect()
+    })
+}
+
+// Children of the non-final positions of `frontier`, with `is_player_one` to
+// move and expanded in turn, leaving out those in `seen`. The work is split
+// over `threads` threads: each takes a slice of the frontier and sorts the
+// children it finds into shards by board, then each shard is deduplicated and
+// expanded on its own. The result only depends on the boards, not on how
+// they were split.
+fn expand_frontier(
+    frontier: &HashMap<Board, GameResult>,
+    seen: &[&HashMap<Board, GameResult>],
+    is_player_one: bool,
+    threads: usize,
+) -> HashMap<Board, GameResult> {
+    let parents: Vec<&GameResult> = frontier.values().collect();
+    let chunk_size = parents.len().div_ceil(threads).max(1);
+    let shard =
+        |board: &Board| (board.0.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize % threads;
+
+    let found: Vec<Vec<Vec<Board>>> = thread::scope(|scope| {
+        let workers: Vec<_> = parents
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut shards = vec![vec![]; threads];
+                    for game_result in chunk {
+                        if let GameResult::Intermediate(game_result_board_vec) = game_result {
+                            for (_, board) in game_result_board_vec {
+                                let (board, _) = board.canonical();
+                                if !seen.iter().any(|level| level.contains_key(&board)) {
+                                    shards[shard(&board)].push(board);
+                                }
+                            }
+                        }
+                    }
+                    shards
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let expanded: Vec<HashMap<Board, GameResult>> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|i| {
+                let found = &found;
+                scope.spawn(move || {
+                    let mut next = HashMap::new();
+                    for board in found.iter().flat_map(|shards| shards[i].iter()) {
+                        next.entry(*board)
+                            .or_insert_with(|| board.get_next_states_2(is_player_one));
+                    }
+                    next
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().unwrap()).collect()
+    });
+
+    let mut next_hashmap = HashMap::with_capacity(expanded.iter().map(HashMap::len).sum());
+    for shard in expanded {
+        next_hashmap.extend(shard);
+    }
+    next_hashmap
 }
 
 // fn duplicate_checker(calc_state: &HashMap<u8, HashMap<Board, GameResult>>) {
@@ -179,70 +572,138 @@ fn sequential_comp() {
 //     info!("is : {}", all_black.len());
 // }
 
-fn calc_proba(is_player_one: bool, calc_state: &HashMap<u8, HashMap<Board, GameResult>>) {
+// Side to move `depth` plies after the root.
+fn side_at(root_is_player_1: bool, depth: u8) -> bool {
+    root_is_player_1 == depth.is_multiple_of(2)
+}
+
+// Evaluation of a board reached `depth` plies from the root, as the chances
+// of `is_player_one` winning.
+fn leaf_proba(
+    evaluator: &dyn Evaluator,
+    board: Board,
+    root_is_player_1: bool,
+    depth: u8,
+    is_player_one: bool,
+) -> f32 {
+    let state = GameState::new(board, side_at(root_is_player_1, depth));
+    let proba = evaluator.evaluate(&state);
+    if state.is_player_1 == is_player_one {
+        proba
+    } else {
+        1f32 - proba
+    }
+}
+
+fn calc_proba(
+    is_player_one: bool,
+    root_is_player_1: bool,
+    max_depth: u8,
+    calc_state: &dyn Levels,
+    evaluator: &dyn Evaluator,
+    checkpoint: Option<&Checkpoint>,
+    progress: &mut Progress,
+) -> io::Result<HashMap<Board, (f32, NextMove)>> {
+    let phase = if is_player_one { "white" } else { "black" };
+    let proba_size = mem::size_of::<(Board, (f32, NextMove))>() as u64;
     let probas_mine: Arc<RwLock<HashMap<Board, (f32, NextMove)>>> =
         Arc::new(RwLock::new(HashMap::default()));
     let probas_theirs: Arc<RwLock<HashMap<Board, (f32, NextMove)>>> =
         Arc::new(RwLock::new(HashMap::default()));
-    let is_ours_to_play = if is_player_one {
-        (MAX_DEPTH - 1) % 2 == 0
-    } else {
-        (MAX_DEPTH - 1) % 2 != 0
+
+    // levels already back-propagated by a previous run, replayed in the order
+    // they were computed
+    let restored = checkpoint
+        .map(|checkpoint| checkpoint.load_probas(is_player_one, max_depth))
+        .unwrap_or_default();
+    for (depth, probas) in restored.iter() {
+        info!("Restoring {}", depth);
+        let list_ref = if side_at(root_is_player_1, *depth) == is_player_one {
+            &probas_mine
+        } else {
+            &probas_theirs
+        };
+        let mut list_guard = list_ref.write().unwrap();
+        for (board, proba, next) in probas {
+            list_guard.insert(*board, (*proba, *next));
+        }
+    }
+    let done_to = restored.last().map_or(max_depth, |(depth, _)| *depth);
+    let save = |depth: u8, list_ref: &RwLock<HashMap<Board, (f32, NextMove)>>| match checkpoint {
+        Some(checkpoint) => {
+            let list_guard = list_ref.read().unwrap();
+            let mut probas = vec![];
+            calc_state.for_each_board(depth, &mut |b| {
+                probas.push((*b, list_guard[b].0, list_guard[b].1))
+            })?;
+            checkpoint.save_probas(is_player_one, depth, &probas)
+        }
+        None => Ok(()),
     };
 
+    let is_ours_to_play = side_at(root_is_player_1, max_depth - 1) == is_player_one;
+
     let list_ref = if is_ours_to_play {
         probas_mine.clone()
     } else {
         probas_theirs.clone()
     };
 
-    for (b, game_result) in calc_state.get(&(MAX_DEPTH - 1)).unwrap() {
-        let mut list_guard = list_ref.write().unwrap();
-        match game_result {
-            GameResult::WhiteWin => {
-                let a;
-                if let Some(old) = if is_player_one {
-                    a = 1;
-                    list_guard.insert(*b, (1f32, NextMove(0)))
-                } else {
-                    a = 0;
-                    list_guard.insert(*b, (0f32, NextMove(0)))
-                } {
-                    error!("HASHMAP ERROR");
-                    error!("{:X} already in probas WW", b.0);
-                    error!("was {}, {:x}", old.0, old.1);
-                    error!("is {}, {:x}", a, 0);
+    if done_to == max_depth {
+        progress.start(phase, (max_depth - 1) as u32, proba_size);
+        calc_state.for_each(max_depth - 1, &mut |b, game_result| {
+            progress.add(1);
+            let mut list_guard = list_ref.write().unwrap();
+            match game_result {
+                GameResult::WhiteWin => {
+                    let a;
+                    if let Some(old) = if is_player_one {
+                        a = 1;
+                        list_guard.insert(*b, (1f32, NextMove(0)))
+                    } else {
+                        a = 0;
+                        list_guard.insert(*b, (0f32, NextMove(0)))
+                    } {
+                        error!("HASHMAP ERROR");
+                        error!("{:X} already in probas WW", b.0);
+                        error!("was {}, {:x}", old.0, old.1);
+                        error!("is {}, {:x}", a, 0);
+                    }
                 }
-            }
 
-            GameResult::BlackWin => {
-                let a;
-                if let Some(old) = if is_player_one {
-                    a = 0;
-                    list_guard.insert(*b, (0f32, NextMove(0)))
-                } else {
-                    a = 1;
-                    list_guard.insert(*b, (1f32, NextMove(0)))
-                } {
-                    error!("HASHMAP ERROR");
-                    error!("{:X} already in probas BW", b.0);
-                    error!("was {}, {:x}", old.0, old.1);
-                    error!("is {}, {:x}", a, 0);
+                GameResult::BlackWin => {
+                    let a;
+                    if let Some(old) = if is_player_one {
+                        a = 0;
+                        list_guard.insert(*b, (0f32, NextMove(0)))
+                    } else {
+                        a = 1;
+                        list_guard.insert(*b, (1f32, NextMove(0)))
+                    } {
+                        error!("HASHMAP ERROR");
+                        error!("{:X} already in probas BW", b.0);
+                        error!("was {}, {:x}", old.0, old.1);
+                        error!("is {}, {:x}", a, 0);
+                    }
+                }
+                GameResult::Intermediate(_) => {
+                    let proba = leaf_proba(
+                        evaluator,
+                        *b,
+                        root_is_player_1,
+                        max_depth - 1,
+                        is_player_one,
+                    );
+                    list_guard.insert(*b, (proba, NextMove(0)));
                 }
             }
-            GameResult::Intermediate(_) => {
-                list_guard.insert(*b, (0.5f32, NextMove(0)));
-            }
-        }
+        })?;
+        save(max_depth - 1, &list_ref)?;
     }
 
-    for depth in (0..MAX_DEPTH - 1).rev() {
+    for depth in (0..done_to.min(max_depth - 1)).rev() {
         info!("{}", depth);
-        let is_our_turn = if is_player_one {
-            depth % 2 == 0
-        } else {
-            depth % 2 != 0
-        };
+        let is_our_turn = side_at(root_is_player_1, depth) == is_player_one;
 
         let list_ref = if is_our_turn {
             probas_mine.clone()
@@ -256,9 +717,9 @@ fn calc_proba(is_player_one: bool, calc_state: &HashMap<u8, HashMap<Board, GameR
             probas_mine.clone()
         };
 
-        let current_hashmap: &HashMap<Board, GameResult> = calc_state.get(&depth).unwrap();
-
-        for (b, game_result) in current_hashmap.iter() {
+        progress.start(phase, depth as u32, proba_size);
+        calc_state.for_each(depth, &mut |b, game_result| {
+            progress.add(1);
             let mut list_guard_mine = list_ref.write().unwrap();
             let probas_theirs = list_ref_ennemy.read().unwrap();
             match game_result {
@@ -294,12 +755,21 @@ fn calc_proba(is_player_one: bool, calc_state: &HashMap<u8, HashMap<Board, GameR
                     let mut best_move = (-2f32, NextMove(0));
                     if is_our_turn {
                         for (next, board) in board_vec {
-                            if let Some(probas_tuple) = probas_theirs.get(board) {
+                            if let Some(probas_tuple) = probas_theirs.get(&board.canonical().0) {
                                 if probas_tuple.0 > best_move.0 {
                                     best_move = (probas_tuple.0, *next)
                                 }
-                            } else if 0.5f32 > best_move.0 {
-                                best_move = (0.5f32, *next)
+                            } else {
+                                let proba = leaf_proba(
+                                    evaluator,
+                                    *board,
+                                    root_is_player_1,
+                                    depth + 1,
+                                    is_player_one,
+                                );
+                                if proba > best_move.0 {
+                                    best_move = (proba, *next)
+                                }
                             }
                         }
                         if let Some(_tuple) = list_guard_mine.insert(*b, best_move) {
@@ -315,11 +785,17 @@ fn calc_proba(is_player_one: bool, calc_state: &HashMap<u8, HashMap<Board, GameR
                         let mut board_number = 0f32;
 
                         for (_, board) in board_vec {
-                            if let Some(probas_tuple) = probas_theirs.get(board) {
+                            if let Some(probas_tuple) = probas_theirs.get(&board.canonical().0) {
                                 proba_sum += probas_tuple.0;
                                 board_number += 1f32;
                             } else {
-                                proba_sum += 0.5f32;
+                                proba_sum += leaf_proba(
+                                    evaluator,
+                                    *board,
+                                    root_is_player_1,
+                                    depth + 1,
+                                    is_player_one,
+                                );
                                 board_number += 1f32;
                             }
                         }
@@ -338,21 +814,56 @@ fn calc_proba(is_player_one: bool, calc_state: &HashMap<u8, HashMap<Board, GameR
                     }
                 }
             }
-        }
+        })?;
+        save(depth, &list_ref)?;
     }
 
-    let mut f = if is_player_one {
-        std::fs::File::create("white_probas_max.txt").unwrap()
-    } else {
-        std::fs::File::create("black_probas_max.txt").unwrap()
-    };
+    progress.finish();
+    let probas_mine = mem::take(&mut *probas_mine.write().unwrap());
+    Ok(probas_mine)
+}
 
-    for (board, (_, next)) in probas_mine.read().unwrap().iter() {
-        if next.0 != 0 {
-            f.write_all(format!("{:X} {:X}\n", board.0, next).as_bytes())
-                .unwrap();
-        }
+// Writes the moves `calc_proba` found for `is_player_one`.
+fn write_probas(
+    is_player_one: bool,
+    max_depth: u8,
+    probas_mine: &HashMap<Board, (f32, NextMove)>,
+    output: &Path,
+    format: Format,
+) -> io::Result<()> {
+    if format == Format::Binary {
+        let entries = probas_mine
+            .iter()
+            .filter(|(_, (_, next))| next.0 != 0)
+            .map(|(board, (proba, next))| TableEntry {
+                board: *board,
+                next_move: *next,
+                value: TableValue::Probability(*proba),
+            })
+            .collect();
+        let side = if is_player_one {
+            SIDE_PLAYER_1
+        } else {
+            SIDE_PLAYER_2
+        };
+        return write_table(
+            output,
+            TableHeader::new(max_depth, side, FLAG_MIRROR),
+            entries,
+        );
+    }
+    // sorted, so that runs give the same file
+    let mut lines: Vec<(&Board, &NextMove)> = probas_mine
+        .iter()
+        .filter(|(_, (_, next))| next.0 != 0)
+        .map(|(board, (_, next))| (board, next))
+        .collect();
+    lines.sort_by_key(|(board, _)| board.0);
+    let mut f = BufWriter::new(File::create(output)?);
+    for (board, next) in lines {
+        f.write_all(format!("{:X} {:X}\n", board.0, next).as_bytes())?;
     }
+    f.flush()
 }
 
 // }
@@ -1,157 +1,199 @@
-use std::env;
 use std::error::Error;
-use std::io::Write;
-
-use game_helper::board::Board;
-use game_helper::moves::{MOVE_E, MOVE_N, MOVE_NE, MOVE_NW, MOVE_S, MOVE_SE, MOVE_SW, MOVE_W};
-use game_helper::piece::{Color, Piece, PieceType};
-use game_helper::structs::GameError::{CantMoveAnywhere, EmptyCemetary, InavlidPiece, InvalidMove};
-use log::{error, info, warn};
-
-pub fn play_game() {
-    let mut b = Board::init();
-
-    env::set_var("RUST_LOG", "info");
-    pretty_env_logger::init();
-    while !b.is_game_over() {
-        // clear the terminal output
-        print!("\x1B[2J\x1B[1;1H");
-        flush();
-
-        warn!("{:?}'s turn", b.get_turn());
-        b.show();
-        info!("Enter piece to move or write 'drop' to drop one from your cemetery: ");
-
-        let available_pieces = b.get_curent_player_piece_list();
-        for p in &available_pieces {
-            print!("{}, ", p.show());
-        }
-        print!("\n\n");
-        flush();
+use std::io::{self, BufRead, Write};
+
+use log::warn;
+
+use game_helper_v2::game_state::GameState;
+use game_helper_v2::history::GameHistory;
+use game_helper_v2::next_move::NextMove;
+use game_helper_v2::piece::EMPTY;
+use game_helper_v2::search::{SearchLimits, Searcher};
+use game_helper_v2::structs::Position;
+use game_helper_v2::tablebase::Tablebase;
+
+use crate::cli::{Options, Side};
+
+const DEFAULT_ENGINE_DEPTH: u8 = 8;
+
+const HELP: &str = "\
+Enter a move as the square of a piece and its target, `b2b3` or `c b2-b3`, or
+a drop from your hand, `C*a1`. The piece letter of a drop can be left out when
+the pieces in hand are all of the same kind.
+  moves   list the legal moves
+  undo    take back the last move (against the engine, the engine's too)
+  board   show the board again
+  help    print this message
+  quit    stop the game";
+
+// Moves of the side the human does not play: the move of `--table` when it
+// has the position and the move is legal, otherwise the best move of an
+// alpha-beta search, or the first legal move if the search stops before
+// finding one.
+struct Engine {
+    searcher: Searcher,
+    tablebase: Option<Tablebase>,
+}
 
-        match choose_piece(&b) {
-            Ok(mut piece) => {
-                if let Err(e) = move_piece(&mut b, &mut piece) {
-                    error!("Invalid move: {}", e);
-                    wait_for_input();
-                }
-            }
-            Err(e) => {
-                if e != "drop" {
-                    error!("Invalid piece: {}", e);
-                    wait_for_input();
-                    continue;
-                }
-                if let Err(e) = drop_piece(&mut b) {
-                    error!("Invalid drop: {}", e);
-                    wait_for_input();
-                }
+impl Engine {
+    fn best_move(&mut self, state: GameState) -> Option<NextMove> {
+        let from_table = self
+            .tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.best_move(&state.board, state.is_player_1));
+        if let Some(next_move) = from_table {
+            match state.apply_move(next_move) {
+                Ok(_) => return Some(next_move),
+                Err(e) => warn!("ignoring the table move {:X}: {}", next_move, e),
             }
         }
+        self.searcher
+            .search(state)
+            .best_move
+            .or_else(|| state.legal_moves().first().copied())
     }
-    game_over(&b);
-}
-
-fn flush() {
-    std::io::stdout().flush().unwrap();
-}
-
-fn wait_for_input() {
-    let mut input = String::new();
-    info!("Press enter to continue...");
-    std::io::stdin().read_line(&mut input).unwrap();
 }
 
-fn choose_piece(board: &Board) -> Result<Piece, String> {
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-
-    match input.trim().to_lowercase().as_str() {
-        "c" => Ok(Piece::new(PieceType::Chick, board.get_turn(), false)),
-        "c*" => Ok(Piece::new(PieceType::Chick, board.get_turn(), true)),
-        "g" => Ok(Piece::new(PieceType::Giraffe, board.get_turn(), false)),
-        "g*" => Ok(Piece::new(PieceType::Giraffe, board.get_turn(), true)),
-        "e" => Ok(Piece::new(PieceType::Elephant, board.get_turn(), false)),
-        "e*" => Ok(Piece::new(PieceType::Elephant, board.get_turn(), true)),
-        "l" => Ok(Piece::new(PieceType::Lion, board.get_turn(), false)),
-        "l*" => Ok(Piece::new(PieceType::Lion, board.get_turn(), true)),
-        "h" => Ok(Piece::new(PieceType::Hen, board.get_turn(), false)),
-        "h*" => Ok(Piece::new(PieceType::Hen, board.get_turn(), true)),
-        _ => Err(input.trim().to_lowercase()),
+fn side_name(is_player_1: bool) -> &'static str {
+    if is_player_1 {
+        "White"
+    } else {
+        "Black"
     }
 }
 
-fn drop_piece(b: &mut Board) -> Result<(), Box<dyn Error>> {
-    let available_pieces = b.get_curent_player_cemetery();
-
-    if available_pieces.is_empty() {
-        return Err(EmptyCemetary.into());
-    }
-
-    info!("Enter piece to drop: ");
-    for p in &available_pieces {
-        print!("{}, ", p.show());
+// The board from rank 4 down to rank 1, player 1's pieces in lower case, and
+// the pieces in hand.
+fn show(state: &GameState) {
+    let rows = state.board.get_state_processed();
+    println!();
+    for (y, row) in rows.iter().enumerate().rev() {
+        let squares: Vec<String> = row
+            .iter()
+            .map(|piece| {
+                if *piece == EMPTY {
+                    ".".to_string()
+                } else {
+                    piece.to_string()
+                }
+            })
+            .collect();
+        println!("{}  {}", y + 1, squares.join(" "));
     }
-    print!("\n\n");
-    std::io::stdout().flush().unwrap();
-
-    match choose_piece(b) {
-        Ok(mut p) => drop_piece_coors(b, &mut p),
-        Err(_) => Err(InavlidPiece.into()),
+    println!("   a b c");
+    for is_player_1 in [true, false] {
+        let hand: Vec<String> = state
+            .board
+            .get_state()
+            .iter()
+            .filter(|(piece, pos)| *pos == Position::Dead && piece.is_mine(is_player_1))
+            .map(|(piece, _)| piece.to_string())
+            .collect();
+        if !hand.is_empty() {
+            println!("{} hand: {}", side_name(is_player_1), hand.join(" "));
+        }
     }
+    println!();
 }
 
-fn drop_piece_coors(b: &mut Board, piece: &mut Piece) -> Result<(), Box<dyn Error>> {
-    info!("Enter coors to drop piece: (without spaces)");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-
-    let coors: usize = input.trim().parse()?;
-
-    let x = coors / 10;
-    let y = coors % 10;
-
-    b.drop_piece(piece, x, y)
+fn list_moves(state: &GameState) {
+    let moves: Vec<String> = state
+        .legal_moves()
+        .iter()
+        .map(|next_move| next_move.notation(&state.board))
+        .collect();
+    println!("{}", moves.join(", "));
 }
 
-fn move_piece(b: &mut Board, piece: &mut Piece) -> Result<(), Box<dyn Error>> {
-    println!("Enter move type: ");
-
-    let m = b.get_legal_move_list_for_piece(piece)?;
-
-    if m.is_empty() {
-        return Err(CantMoveAnywhere.into());
+// Takes back moves until it is a human's turn again, at least one of them.
+fn undo(history: &mut GameHistory, side: Side) {
+    if history.undo().is_none() {
+        println!("nothing to undo");
+        return;
     }
+    while !side.includes(history.current().is_player_1) && history.undo().is_some() {}
+    show(&history.current());
+}
 
-    for m in m.iter() {
-        print!("{}, ", m);
-    }
-
-    print!("\n\n");
-    std::io::stdout().flush().unwrap();
-
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
-    let move_type = match input.trim().to_lowercase().as_str() {
-        "n" => MOVE_N,
-        "ne" => MOVE_NE,
-        "e" => MOVE_E,
-        "se" => MOVE_SE,
-        "s" => MOVE_S,
-        "sw" => MOVE_SW,
-        "w" => MOVE_W,
-        "nw" => MOVE_NW,
-        _ => return Err(InvalidMove.into()),
+// Plays a game in the terminal from `--root`. With `--side white` or `--side
+// black` the human plays that side against the engine, with `both` two humans
+// take turns at the keyboard.
+pub fn play(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut engine = if options.side == Side::Both {
+        None
+    } else {
+        let limits = SearchLimits {
+            max_depth: options.depth.unwrap_or(DEFAULT_ENGINE_DEPTH),
+            ..SearchLimits::default()
+        };
+        let tablebase = match &options.table {
+            Some(path) => {
+                Some(Tablebase::open(path).map_err(|e| format!("{}: {}", path.display(), e))?)
+            }
+            None => None,
+        };
+        Some(Engine {
+            searcher: Searcher::new(limits),
+            tablebase,
+        })
     };
 
-    b.move_piece(piece, move_type)
-}
+    let mut history = GameHistory::new(options.root);
+    let mut lines = io::stdin().lock().lines();
+    println!("Type `help` for the commands.");
+    show(&history.current());
+    while history.outcome().is_none() {
+        let state = history.current();
+        if let Some(engine) = engine
+            .as_mut()
+            .filter(|_| !options.side.includes(state.is_player_1))
+        {
+            // a side without moves has lost, so there is one
+            let next_move = match engine.best_move(state) {
+                Some(next_move) => next_move,
+                None => {
+                    println!("The engine found no move");
+                    break;
+                }
+            };
+            println!(
+                "{} plays {}",
+                side_name(state.is_player_1),
+                next_move.notation(&state.board)
+            );
+            if let Err(e) = history.play(next_move) {
+                println!("The engine played an illegal move: {}", e);
+                break;
+            }
+            show(&history.current());
+            continue;
+        }
+
+        print!("{} to move> ", side_name(state.is_player_1));
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        match line.trim() {
+            "" => {}
+            "quit" | "exit" => break,
+            "help" => println!("{}", HELP),
+            "board" => show(&state),
+            "moves" => list_moves(&state),
+            "undo" => undo(&mut history, options.side),
+            text => match NextMove::parse(text, &state.board, state.is_player_1) {
+                Ok(next_move) => {
+                    history.play(next_move)?;
+                    show(&history.current());
+                }
+                Err(e) => println!("{}: {}", text, e),
+            },
+        }
+    }
 
-fn game_over(b: &Board) {
-    info!("Game Over");
-    match b.winner {
-        Some(Color::White) => info!("White wins"),
-        Some(Color::Black) => info!("Black wins"),
-        None => info!("It's a draw (somehow)"),
+    match history.outcome() {
+        Some(outcome) => println!("{} after {} moves", outcome, history.moves().len()),
+        None => println!("Game stopped after {} moves", history.moves().len()),
     }
+    Ok(())
 }
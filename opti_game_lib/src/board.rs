@@ -10,7 +10,7 @@ use piece::{
 };
 use structs::GameResult::{BlackWin, Intermediate};
 use structs::Position::{Dead, X0Y0, X0Y3, X1Y0, X1Y1, X1Y2, X1Y3, X2Y0, X2Y3};
-use structs::{GameResult, Position};
+use structs::{GameError, GameOutcome, GameResult, LegalMove, Position, Termination};
 
 // The `derive` attribute automatically implements the specified traits for the struct.
 // Clone: Allows the struct to be duplicated.
@@ -293,6 +293,162 @@ impl Board {
         Intermediate(boards)
     }
 
+    // Every legal move of the side to move, including the ones that end the
+    // game either way. Unlike `get_next_states_2` this never stops early.
+    pub fn legal_children(&self, is_player_1: bool) -> Vec<LegalMove> {
+        let state = self.get_state();
+        let mut parachuted_pieces = HashSet::new();
+        let state_processed = Self::get_state_processed_from_state(state);
+        let mut children: Vec<LegalMove> = vec![];
+
+        for (piece, pos) in state {
+            if !piece.is_mine(is_player_1) {
+                continue;
+            }
+            if pos == Position::Dead {
+                if !parachuted_pieces.insert(piece) {
+                    continue;
+                }
+                for (y, row) in state_processed.iter().enumerate() {
+                    for (x, piece_at_pos) in row.iter().enumerate() {
+                        if *piece_at_pos != EMPTY {
+                            continue;
+                        }
+                        let next_move =
+                            NextMove::new(piece, pos, Position::from((x as u8, y as u8)));
+                        let board =
+                            self.compute_child_from_nextmove_on_empty_space(next_move, is_player_1);
+                        children.push(LegalMove {
+                            next_move,
+                            board,
+                            winner: board.has_winner(is_player_1),
+                        });
+                    }
+                }
+            } else {
+                let converted_pos = <&Position as Into<(i8, i8)>>::into(&pos);
+                for m in piece.moves() {
+                    let new_converted_pos = (
+                        converted_pos.0 + if is_player_1 { m.x } else { -m.x },
+                        converted_pos.1 + if is_player_1 { m.y } else { -m.y },
+                    );
+                    if !(0..3).contains(&new_converted_pos.0)
+                        || !(0..4).contains(&new_converted_pos.1)
+                    {
+                        continue;
+                    }
+                    let piece_at_new_pos =
+                        state_processed[new_converted_pos.1 as usize][new_converted_pos.0 as usize];
+                    if piece_at_new_pos.is_mine(is_player_1) {
+                        continue;
+                    }
+                    let next_move = NextMove::new(
+                        piece,
+                        pos,
+                        Position::from((new_converted_pos.0 as u8, new_converted_pos.1 as u8)),
+                    );
+                    let board = if piece_at_new_pos == EMPTY {
+                        self.compute_child_from_nextmove_on_empty_space(next_move, is_player_1)
+                    } else {
+                        self.compute_child_from_nextmove(next_move, is_player_1)
+                    };
+                    children.push(LegalMove {
+                        next_move,
+                        board,
+                        winner: board.has_winner(is_player_1),
+                    });
+                }
+            }
+        }
+        children
+    }
+
+    pub fn legal_moves(&self, is_player_1: bool) -> Vec<NextMove> {
+        self.legal_children(is_player_1)
+            .into_iter()
+            .map(|child| child.next_move)
+            .collect()
+    }
+
+    // Checks `next_move` against the rules before playing it, where
+    // `compute_child_from_nextmove` trusts its input and panics on garbage.
+    pub fn apply_move(&self, next_move: NextMove, is_player_1: bool) -> Result<Board, GameError> {
+        let (piece_id, old_pos, new_pos) = next_move.unpack();
+        if next_move.0 > 0xfff || piece_id == EMPTY.0 || piece_id > HEN_2.0 {
+            return Err(GameError::InavlidPiece);
+        }
+        if old_pos > Dead.as_u8() || new_pos >= Dead.as_u8() {
+            return Err(GameError::OutOfBounds);
+        }
+        let piece = Piece::from(piece_id);
+        if !piece.is_mine(is_player_1) {
+            return Err(GameError::NotYourPiece);
+        }
+        // the previous move already decided the game, unless it left its own
+        // lion hanging, in which case taking it is still to be played
+        if let Some(player_one_won) = self.has_winner(!is_player_1) {
+            if player_one_won != is_player_1 {
+                return Err(GameError::GameOver);
+            }
+        }
+
+        let state = self.get_state();
+        let state_processed = Self::get_state_processed_from_state(state);
+        let new_pos = Position::from(new_pos);
+        let (new_x, new_y) = <&Position as Into<(u8, u8)>>::into(&new_pos);
+        let piece_at_new_pos = state_processed[new_y as usize][new_x as usize];
+
+        if old_pos == Dead.as_u8() {
+            if !state
+                .iter()
+                .any(|(p, pos)| *pos == Dead && p.is_mine(is_player_1))
+            {
+                return Err(GameError::EmptyCemetary);
+            }
+            if !state.iter().any(|(p, pos)| *pos == Dead && *p == piece) {
+                return Err(GameError::PieceNotInBoard);
+            }
+            // a lion never goes to the cemetery, it ends the game
+            if piece == LION_1 || piece == LION_2 {
+                return Err(GameError::IllegalMove);
+            }
+            if piece_at_new_pos != EMPTY {
+                return Err(GameError::IllegalMove);
+            }
+            return Ok(self.compute_child_from_nextmove_on_empty_space(next_move, is_player_1));
+        }
+
+        let old_pos = Position::from(old_pos);
+        let (old_x, old_y) = <&Position as Into<(i8, i8)>>::into(&old_pos);
+        let piece_at_old_pos = state_processed[old_y as usize][old_x as usize];
+        if piece_at_old_pos != piece {
+            return Err(
+                if piece_at_old_pos != EMPTY && !piece_at_old_pos.is_mine(is_player_1) {
+                    GameError::NotYourPiece
+                } else {
+                    GameError::PieceNotInBoard
+                },
+            );
+        }
+
+        let (dx, dy) = (new_x as i8 - old_x, new_y as i8 - old_y);
+        if !piece.moves().iter().any(|m| {
+            (dx, dy)
+                == if is_player_1 {
+                    (m.x, m.y)
+                } else {
+                    (-m.x, -m.y)
+                }
+        }) {
+            return Err(GameError::IllegalMove);
+        }
+        if piece_at_new_pos.is_mine(is_player_1) {
+            return Err(GameError::IllegalMove);
+        }
+
+        Ok(self.compute_child_from_nextmove(next_move, is_player_1))
+    }
+
     // for m in piece.moves() {
     //                 let converted_pos = <&Position as Into<(i8, i8)>>::into(pos);
     //                 let new_pos = (
@@ -594,7 +750,13 @@ impl Board {
                 new_bytes[i] = next;
                 found = true;
             } else if (old_bytes[i] & 0x0f) == new_pos {
-                let piece = (Piece::from((old_bytes[i] & 0xf0) >> 4).get_opposite_except_king()).0;
+                // a hen is taken as a chick
+                let piece = match Piece::from((old_bytes[i] & 0xf0) >> 4) {
+                    HEN_1 => CHICK_1,
+                    HEN_2 => CHICK_2,
+                    piece => piece,
+                };
+                let piece = piece.get_opposite_except_king().0;
                 new_bytes[i] = (piece << 4) + Position::Dead.as_u8();
             }
         }
@@ -610,6 +772,12 @@ impl Board {
     }
 
     pub fn has_winner(&self, is_player_1: bool) -> Option<bool> {
+        self.has_winner_with_reason(is_player_1)
+            .map(|(player_one_won, _)| player_one_won)
+    }
+
+    // Same as `has_winner`, also telling how the game was won.
+    pub fn has_winner_with_reason(&self, is_player_1: bool) -> Option<(bool, Termination)> {
         let state = self.get_state();
 
         let mut found_own_king = false;
@@ -626,7 +794,7 @@ impl Board {
             {
                 found_enemy_king = true;
                 if *pos == Dead {
-                    return if is_player_1 { Some(true) } else { Some(false) };
+                    return Some((is_player_1, Termination::LionCaptured));
                 }
                 enemies.push((*piece, *pos))
             } else if !found_own_king && (is_player_1 && *piece == LION_1)
@@ -650,34 +818,49 @@ impl Board {
         for e in enemies.iter() {
             enemy_pos = <&Position as Into<(i8, i8)>>::into(&e.1);
             if (king_pos.0 - enemy_pos.0).abs() < 2 && (king_pos.1 - enemy_pos.1).abs() < 2 {
+                // the enemy moves in the other direction
                 for m in e.0.moves() {
                     let new_pos = (
-                        enemy_pos.0 + if is_player_1 { m.x } else { -m.x },
-                        enemy_pos.1 + if is_player_1 { m.y } else { -m.y },
+                        enemy_pos.0 + if is_player_1 { -m.x } else { m.x },
+                        enemy_pos.1 + if is_player_1 { -m.y } else { m.y },
                     );
                     // si la piece adjacente peut manger le lion
                     if new_pos == king_pos {
-                        return if !is_player_1 {
-                            Some(true)
-                        } else {
-                            Some(false)
-                        };
+                        return Some((!is_player_1, Termination::LionCaptured));
                     }
                 }
             }
         }
 
         if king_on_winning_row {
-            if is_player_1 {
-                Some(true)
-            } else {
-                Some(false)
-            }
+            Some((is_player_1, Termination::Try))
         } else {
             None
         }
     }
 
+    // How the game stands once `last_move` (if any) has been played by
+    // `is_player_1`, following the `has_winner` convention. On top of
+    // `has_winner`, this reports a loss for the side to move when it is left
+    // without any legal move.
+    pub fn outcome(&self, is_player_1: bool, last_move: Option<NextMove>) -> Option<GameOutcome> {
+        if let Some((player_one_won, termination)) = self.has_winner_with_reason(is_player_1) {
+            return Some(GameOutcome {
+                winner: Some(player_one_won),
+                termination,
+                last_move,
+            });
+        }
+        if self.legal_children(!is_player_1).is_empty() {
+            return Some(GameOutcome {
+                winner: Some(is_player_1),
+                termination: Termination::NoLegalMoves,
+                last_move,
+            });
+        }
+        None
+    }
+
     // fn is_lion_in_danger(is_player_1: bool, state_processed: [[Piece; 3]; 4]) -> bool {
     //     for m in LION_1.moves() {
     //         let check_pos = (
@@ -705,3 +888,41 @@ impl Board {
     //     false
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use board::Board;
+    use game_state::GameState;
+    use next_move::NextMove;
+    use structs::Termination;
+
+    #[test]
+    fn chick_takes_lion_forwards() {
+        // the player 2 chick on b3 takes the player 1 lion on b2
+        let board: Board = "GLE/1C1/1l1/e1g c".parse().unwrap();
+        assert_eq!(
+            board.has_winner_with_reason(true),
+            Some((false, Termination::LionCaptured))
+        );
+        // but not from behind, on b1
+        let board: Board = "GLE/3/1l1/eCg c".parse().unwrap();
+        assert_eq!(board.has_winner(true), None);
+    }
+
+    #[test]
+    fn hen_takes_lion_forwards() {
+        let board: Board = "GLE/1H1/1l1/e1g c".parse().unwrap();
+        assert_eq!(board.has_winner(true), Some(false));
+        // the player 1 hen on a2 moves diagonally up the board to b3
+        let board: Board = "G1E/1L1/h2/elg C".parse().unwrap();
+        assert_eq!(board.has_winner(false), Some(true));
+    }
+
+    #[test]
+    fn hen_is_taken_as_a_chick() {
+        let state = GameState::new("GLE/1H1/1c1/elg -".parse().unwrap(), true);
+        let next_move = NextMove::parse("b2b3", &state.board, true).unwrap();
+        let expected: Board = "GLE/1c1/3/elg c".parse().unwrap();
+        assert_eq!(state.apply_move(next_move).unwrap().board, expected);
+    }
+}
@@ -0,0 +1,232 @@
+use game_state::GameState;
+use piece::{
+    Piece, CHICK_1, CHICK_2, ELEPHANT_1, ELEPHANT_2, GIRAFFE_1, GIRAFFE_2, HEN_1, HEN_2, LION_1,
+    LION_2,
+};
+use structs::Position;
+
+/// Static judgement of a position that is not played out to the end.
+pub trait Evaluator: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Chances of the side to move winning `state`, between 0 and 1.
+    fn evaluate(&self, state: &GameState) -> f32;
+}
+
+// Names accepted by `by_name`.
+pub const EVALUATORS: &[&str] = &[
+    "neutral",
+    "material",
+    "lion-safety",
+    "lion-advancement",
+    "mobility",
+    "standard",
+];
+
+pub fn by_name(name: &str) -> Option<Box<dyn Evaluator>> {
+    match name {
+        "neutral" => Some(Box::new(Neutral)),
+        "material" => Some(Box::new(Material)),
+        "lion-safety" => Some(Box::new(LionSafety)),
+        "lion-advancement" => Some(Box::new(LionAdvancement)),
+        "mobility" => Some(Box::new(Mobility)),
+        "standard" => Some(Box::new(Standard)),
+        _ => None,
+    }
+}
+
+// Turns a score counted in chicks into a probability: being a whole elephant
+// up is worth about 75%.
+fn probability(score: f32) -> f32 {
+    1.0 / (1.0 + (-score / 2.7).exp())
+}
+
+fn piece_value(piece: Piece) -> f32 {
+    match piece {
+        CHICK_1 | CHICK_2 => 1.0,
+        ELEPHANT_1 | ELEPHANT_2 => 3.0,
+        GIRAFFE_1 | GIRAFFE_2 => 4.0,
+        HEN_1 | HEN_2 => 4.5,
+        _ => 0.0,
+    }
+}
+
+fn material_score(state: &GameState) -> f32 {
+    state
+        .board
+        .get_state()
+        .iter()
+        .map(|(piece, pos)| {
+            // a piece in hand can be dropped anywhere
+            let value = piece_value(*piece) * if *pos == Position::Dead { 1.2 } else { 1.0 };
+            if piece.is_mine(state.is_player_1) {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum()
+}
+
+// Number of pieces of each side covering each square, indexed by
+// `Position::as_u8`, player 1 first.
+fn attacks(state: &GameState) -> [[u8; 12]; 2] {
+    let mut attacks = [[0u8; 12]; 2];
+    for (piece, pos) in state.board.get_state() {
+        if pos == Position::Dead {
+            continue;
+        }
+        let is_player_1 = piece.is_mine(true);
+        let (x, y) = <&Position as Into<(i8, i8)>>::into(&pos);
+        for m in piece.moves() {
+            let (x, y) = if is_player_1 {
+                (x + m.x, y + m.y)
+            } else {
+                (x - m.x, y - m.y)
+            };
+            if (0..3).contains(&x) && (0..4).contains(&y) {
+                attacks[!is_player_1 as usize][(y * 3 + x) as usize] += 1;
+            }
+        }
+    }
+    attacks
+}
+
+fn lion_position(state: &GameState, is_player_1: bool) -> (i8, i8) {
+    let lion = if is_player_1 { LION_1 } else { LION_2 };
+    let board = state.board.get_state();
+    let (_, pos) = board.iter().find(|(piece, _)| *piece == lion).unwrap();
+    <&Position as Into<(i8, i8)>>::into(pos)
+}
+
+// Enemy coverage of the lion and of the squares it could flee to, from the
+// point of view of the lion's owner.
+fn lion_danger(state: &GameState, attacks: &[[u8; 12]; 2], is_player_1: bool) -> f32 {
+    let (lx, ly) = lion_position(state, is_player_1);
+    let enemy = &attacks[is_player_1 as usize];
+    let mut danger = 0.0;
+    for y in (ly - 1)..=(ly + 1) {
+        for x in (lx - 1)..=(lx + 1) {
+            if !(0..3).contains(&x) || !(0..4).contains(&y) {
+                continue;
+            }
+            let covered = enemy[(y * 3 + x) as usize] as f32;
+            danger += if (x, y) == (lx, ly) {
+                2.0 * covered
+            } else {
+                0.5 * covered
+            };
+        }
+    }
+    danger
+}
+
+fn lion_safety_score(state: &GameState) -> f32 {
+    let attacks = attacks(state);
+    lion_danger(state, &attacks, !state.is_player_1)
+        - lion_danger(state, &attacks, state.is_player_1)
+}
+
+// Rows the lion has made towards the enemy's back row, where reaching it
+// safely wins the game.
+fn lion_progress(state: &GameState, is_player_1: bool) -> f32 {
+    let (_, y) = lion_position(state, is_player_1);
+    if is_player_1 {
+        y as f32
+    } else {
+        (3 - y) as f32
+    }
+}
+
+fn lion_advancement_score(state: &GameState) -> f32 {
+    0.5 * (lion_progress(state, state.is_player_1) - lion_progress(state, !state.is_player_1))
+}
+
+fn mobility_score(state: &GameState) -> f32 {
+    let mine = state.board.legal_children(state.is_player_1).len() as f32;
+    let theirs = state.board.legal_children(!state.is_player_1).len() as f32;
+    0.1 * (mine - theirs)
+}
+
+/// Every position is a coin toss, which is what `calc_proba` always assumed.
+pub struct Neutral;
+
+impl Evaluator for Neutral {
+    fn name(&self) -> &'static str {
+        "neutral"
+    }
+
+    fn evaluate(&self, _state: &GameState) -> f32 {
+        0.5
+    }
+}
+
+/// Pieces on the board and in hand, the latter a bit more valuable.
+pub struct Material;
+
+impl Evaluator for Material {
+    fn name(&self) -> &'static str {
+        "material"
+    }
+
+    fn evaluate(&self, state: &GameState) -> f32 {
+        probability(material_score(state))
+    }
+}
+
+/// How well each lion and its surroundings are covered by enemy pieces.
+pub struct LionSafety;
+
+impl Evaluator for LionSafety {
+    fn name(&self) -> &'static str {
+        "lion-safety"
+    }
+
+    fn evaluate(&self, state: &GameState) -> f32 {
+        probability(lion_safety_score(state))
+    }
+}
+
+/// How far each lion has walked towards a try.
+pub struct LionAdvancement;
+
+impl Evaluator for LionAdvancement {
+    fn name(&self) -> &'static str {
+        "lion-advancement"
+    }
+
+    fn evaluate(&self, state: &GameState) -> f32 {
+        probability(lion_advancement_score(state))
+    }
+}
+
+/// Difference in the number of legal moves of both sides.
+pub struct Mobility;
+
+impl Evaluator for Mobility {
+    fn name(&self) -> &'static str {
+        "mobility"
+    }
+
+    fn evaluate(&self, state: &GameState) -> f32 {
+        probability(mobility_score(state))
+    }
+}
+
+/// All of the above together.
+pub struct Standard;
+
+impl Evaluator for Standard {
+    fn name(&self) -> &'static str {
+        "standard"
+    }
+
+    fn evaluate(&self, state: &GameState) -> f32 {
+        probability(
+            material_score(state)
+                + lion_safety_score(state)
+                + lion_advancement_score(state)
+                + mobility_score(state),
+        )
+    }
+}
@@ -0,0 +1,117 @@
+use std::hash::{Hash, Hasher};
+
+use board::Board;
+use next_move::NextMove;
+use structs::{GameError, GameOutcome, GameResult, LegalMove};
+
+/// A `Board` together with the side to move.
+///
+/// The packed `Board` has no idea whose turn it is, so this is what should be
+/// passed around instead of a `(Board, bool)` pair. `ply` counts half-moves
+/// played since the position the caller started from; it is not part of the
+/// position itself and is ignored by `Eq` and `Hash`, so a `GameState` can be
+/// used as a map key for transpositions reached at different depths.
+#[derive(Clone, Copy, Debug)]
+pub struct GameState {
+    pub board: Board,
+    pub is_player_1: bool,
+    pub ply: u16,
+}
+
+impl PartialEq for GameState {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board && self.is_player_1 == other.is_player_1
+    }
+}
+
+impl Eq for GameState {}
+
+impl Hash for GameState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.is_player_1.hash(state);
+    }
+}
+
+impl GameState {
+    pub fn new(board: Board, is_player_1: bool) -> GameState {
+        GameState {
+            board,
+            is_player_1,
+            ply: 0,
+        }
+    }
+
+    // Initial position, player 1 to move.
+    pub fn init() -> GameState {
+        GameState::new(Board::init(), true)
+    }
+
+    // Same as `Board::get_next_states_2` for the side to move.
+    pub fn get_next_states_2(&self) -> GameResult {
+        self.board.get_next_states_2(self.is_player_1)
+    }
+
+    pub fn legal_moves(&self) -> Vec<NextMove> {
+        self.board.legal_moves(self.is_player_1)
+    }
+
+    pub fn legal_children(&self) -> Vec<LegalMove> {
+        self.board.legal_children(self.is_player_1)
+    }
+
+    // Checks the position for the player who just moved into it, which is the
+    // convention `Board::has_winner` expects. `Some(true)` means player 1 won.
+    pub fn has_winner(&self) -> Option<bool> {
+        self.board.has_winner(!self.is_player_1)
+    }
+
+    // Whether the game is over in this state, `last_move` being the move
+    // that led to it.
+    pub fn outcome(&self, last_move: Option<NextMove>) -> Option<GameOutcome> {
+        self.board.outcome(!self.is_player_1, last_move)
+    }
+
+    // Plays `next_move` for the side to move and hands the turn over.
+    pub fn compute_child(&self, next_move: NextMove) -> GameState {
+        GameState {
+            board: self
+                .board
+                .compute_child_from_nextmove(next_move, self.is_player_1),
+            is_player_1: !self.is_player_1,
+            ply: self.ply + 1,
+        }
+    }
+
+    // Same as `compute_child` but checks the move first, see `Board::apply_move`.
+    pub fn apply_move(&self, next_move: NextMove) -> Result<GameState, GameError> {
+        Ok(GameState {
+            board: self.board.apply_move(next_move, self.is_player_1)?,
+            is_player_1: !self.is_player_1,
+            ply: self.ply + 1,
+        })
+    }
+
+    // Children of this state as returned by `get_next_states_2`, or `None` if
+    // the side to move has a winning move.
+    pub fn children(&self) -> Option<Vec<(NextMove, GameState)>> {
+        match self.get_next_states_2() {
+            GameResult::WhiteWin | GameResult::BlackWin => None,
+            GameResult::Intermediate(boards) => Some(
+                boards
+                    .into_iter()
+                    .map(|(next_move, board)| {
+                        (
+                            next_move,
+                            GameState {
+                                board,
+                                is_player_1: !self.is_player_1,
+                                ply: self.ply + 1,
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
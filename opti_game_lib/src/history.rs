@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use game_state::GameState;
+use next_move::NextMove;
+use structs::{GameError, GameOutcome, Termination};
+
+/// Draw rules applied by `GameHistory`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DrawRule {
+    // a position reached this many times is a draw, 0 disables the check
+    pub repetitions: u8,
+    // number of plies after which the game is a draw, `None` for no limit
+    pub max_plies: Option<u16>,
+}
+
+impl Default for DrawRule {
+    fn default() -> Self {
+        DrawRule {
+            repetitions: 3,
+            max_plies: None,
+        }
+    }
+}
+
+/// The positions and moves of a game, from its start position to the
+/// current one, with the number of times each position was reached.
+#[derive(Debug, Clone)]
+pub struct GameHistory {
+    states: Vec<GameState>,
+    moves: Vec<NextMove>,
+    seen: HashMap<GameState, u8>,
+    rule: DrawRule,
+    outcome: Option<GameOutcome>,
+}
+
+impl GameHistory {
+    pub fn new(start: GameState) -> GameHistory {
+        GameHistory::with_rule(start, DrawRule::default())
+    }
+
+    pub fn with_rule(start: GameState, rule: DrawRule) -> GameHistory {
+        let mut seen = HashMap::new();
+        seen.insert(start, 1);
+        GameHistory {
+            states: vec![start],
+            moves: vec![],
+            seen,
+            rule,
+            outcome: start.outcome(None),
+        }
+    }
+
+    pub fn current(&self) -> GameState {
+        *self.states.last().unwrap()
+    }
+
+    pub fn start(&self) -> GameState {
+        self.states[0]
+    }
+
+    pub fn states(&self) -> &[GameState] {
+        &self.states
+    }
+
+    pub fn moves(&self) -> &[NextMove] {
+        &self.moves
+    }
+
+    pub fn rule(&self) -> DrawRule {
+        self.rule
+    }
+
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.outcome
+    }
+
+    // Number of times `state` was reached in this game so far.
+    pub fn repetition_count(&self, state: &GameState) -> u8 {
+        *self.seen.get(state).unwrap_or(&0)
+    }
+
+    pub fn current_repetitions(&self) -> u8 {
+        self.repetition_count(&self.current())
+    }
+
+    // Whether reaching `state` next would end the game by repetition.
+    pub fn would_repeat(&self, state: &GameState) -> bool {
+        self.rule.repetitions > 0 && self.repetition_count(state) + 1 >= self.rule.repetitions
+    }
+
+    // Checks and plays `next_move`, returning the outcome if it ended the game.
+    pub fn play(&mut self, next_move: NextMove) -> Result<Option<GameOutcome>, GameError> {
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        let state = self.current().apply_move(next_move)?;
+
+        let count = self.seen.entry(state).or_insert(0);
+        *count += 1;
+        let count = *count;
+        self.states.push(state);
+        self.moves.push(next_move);
+
+        self.outcome = state.outcome(Some(next_move));
+        if self.outcome.is_none() && self.rule.repetitions > 0 && count >= self.rule.repetitions {
+            self.outcome = Some(GameOutcome {
+                winner: None,
+                termination: Termination::Repetition,
+                last_move: Some(next_move),
+            });
+        }
+        if self.outcome.is_none() && self.rule.max_plies.is_some_and(|max| state.ply >= max) {
+            self.outcome = Some(GameOutcome {
+                winner: None,
+                termination: Termination::MoveLimit,
+                last_move: Some(next_move),
+            });
+        }
+        Ok(self.outcome)
+    }
+
+    // Takes back the last move, if any.
+    pub fn undo(&mut self) -> Option<NextMove> {
+        let next_move = self.moves.pop()?;
+        let state = self.states.pop().unwrap();
+        if let Some(count) = self.seen.get_mut(&state) {
+            *count -= 1;
+            if *count == 0 {
+                self.seen.remove(&state);
+            }
+        }
+        // a move was played from the previous state, so it was not over
+        self.outcome = None;
+        Some(next_move)
+    }
+}
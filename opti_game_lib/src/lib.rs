@@ -1,7 +1,24 @@
 extern crate log;
+extern crate memmap2;
+extern crate rand;
 
 pub mod board;
+pub mod eval;
+pub mod game_state;
+pub mod history;
+pub mod mcts;
 pub mod moves;
 pub mod next_move;
+pub mod notation;
+pub mod perft;
 pub mod piece;
+pub mod progress;
+pub mod rank;
+pub mod record;
+pub mod retrograde;
+pub mod search;
 pub mod structs;
+pub mod symmetry;
+pub mod table;
+pub mod tablebase;
+pub mod verify;
@@ -0,0 +1,277 @@
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use game_state::GameState;
+use next_move::NextMove;
+use structs::LegalMove;
+
+/// How moves are picked when a game is played out from a new node.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rollout {
+    // any legal move, uniformly
+    Random,
+    // a winning move when there is one, otherwise any move that does not
+    // lose on the spot
+    Greedy,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct MctsConfig {
+    pub iterations: u64,
+    pub max_time: Option<Duration>,
+    // UCT exploration constant
+    pub exploration: f64,
+    pub rollout: Rollout,
+    // a playout still going after this many plies counts as a draw
+    pub max_rollout_plies: u16,
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            iterations: 10_000,
+            max_time: None,
+            exploration: std::f64::consts::SQRT_2,
+            rollout: Rollout::Random,
+            max_rollout_plies: 200,
+            seed: 0,
+        }
+    }
+}
+
+/// What the search found out about one move from the root. `wins` counts a
+/// draw as half a win, for the player making the move.
+#[derive(Debug, Copy, Clone)]
+pub struct MoveStats {
+    pub next_move: NextMove,
+    pub visits: u64,
+    pub wins: f64,
+}
+
+impl MoveStats {
+    pub fn win_rate(&self) -> f64 {
+        if self.visits == 0 {
+            return 0.0;
+        }
+        self.wins / self.visits as f64
+    }
+}
+
+// The modulo bias is negligible for the few dozen moves of a position.
+fn random_index(rng: &mut StdRng, len: usize) -> usize {
+    (rng.next_u64() % len as u64) as usize
+}
+
+struct Node {
+    state: GameState,
+    next_move: Option<NextMove>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<LegalMove>,
+    // `Some(player_one_won)` once the game is over in this node
+    winner: Option<bool>,
+    visits: u64,
+    // for the player who moved into this node
+    wins: f64,
+}
+
+impl Node {
+    fn new(state: GameState, next_move: Option<NextMove>, parent: Option<usize>) -> Node {
+        let untried = state.legal_children();
+        // with no move left the side to move has lost
+        let winner = if untried.is_empty() {
+            Some(!state.is_player_1)
+        } else {
+            None
+        };
+        Node {
+            state,
+            next_move,
+            parent,
+            children: vec![],
+            untried,
+            winner,
+            visits: 0,
+            wins: 0.0,
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.winner.is_some()
+    }
+}
+
+/// Monte Carlo tree search with UCT selection. The tree is rebuilt on every
+/// call to `search`, and the statistics of the last search stay available
+/// through `stats` until the next one.
+pub struct Mcts {
+    pub config: MctsConfig,
+    rng: StdRng,
+    nodes: Vec<Node>,
+}
+
+impl Mcts {
+    pub fn new(config: MctsConfig) -> Mcts {
+        Mcts {
+            config,
+            rng: StdRng::seed_from_u64(config.seed),
+            nodes: vec![],
+        }
+    }
+
+    // Runs until the iteration or time budget is spent and returns the most
+    // visited move, `None` if `root` has no legal move.
+    pub fn search(&mut self, root: GameState) -> Option<NextMove> {
+        self.nodes = vec![Node::new(root, None, None)];
+        let start = Instant::now();
+        for _ in 0..self.config.iterations {
+            if self
+                .config
+                .max_time
+                .is_some_and(|max| start.elapsed() >= max)
+            {
+                break;
+            }
+            self.iterate();
+        }
+        self.best_move()
+    }
+
+    pub fn iterations(&self) -> u64 {
+        self.nodes.first().map_or(0, |root| root.visits)
+    }
+
+    pub fn best_move(&self) -> Option<NextMove> {
+        self.stats().first().map(|stats| stats.next_move)
+    }
+
+    // One entry per root move explored so far, most visited first.
+    pub fn stats(&self) -> Vec<MoveStats> {
+        let mut stats: Vec<MoveStats> = match self.nodes.first() {
+            Some(root) => root
+                .children
+                .iter()
+                .map(|&i| {
+                    let node = &self.nodes[i];
+                    MoveStats {
+                        next_move: node.next_move.unwrap(),
+                        visits: node.visits,
+                        wins: node.wins,
+                    }
+                })
+                .collect(),
+            None => vec![],
+        };
+        stats.sort_by_key(|stats| std::cmp::Reverse(stats.visits));
+        stats
+    }
+
+    fn iterate(&mut self) {
+        let mut current = 0;
+
+        // selection
+        while !self.nodes[current].is_terminal() && self.nodes[current].untried.is_empty() {
+            current = self.select_child(current);
+        }
+
+        // expansion
+        if !self.nodes[current].is_terminal() {
+            let untried = &mut self.nodes[current].untried;
+            let child = untried.swap_remove(random_index(&mut self.rng, untried.len()));
+            let is_player_1 = self.nodes[current].state.is_player_1;
+            let mut node = Node::new(
+                GameState::new(child.board, !is_player_1),
+                Some(child.next_move),
+                Some(current),
+            );
+            if child.winner.is_some() {
+                node.winner = child.winner;
+                node.untried.clear();
+            }
+            self.nodes.push(node);
+            let index = self.nodes.len() - 1;
+            self.nodes[current].children.push(index);
+            current = index;
+        }
+
+        // simulation
+        let node = &self.nodes[current];
+        let winner = match node.winner {
+            Some(player_one_won) => Some(player_one_won),
+            None => self.rollout(node.state),
+        };
+
+        // backpropagation
+        let mut next = Some(current);
+        while let Some(i) = next {
+            let node = &mut self.nodes[i];
+            node.visits += 1;
+            node.wins += match winner {
+                Some(player_one_won) if player_one_won != node.state.is_player_1 => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            next = node.parent;
+        }
+    }
+
+    fn select_child(&self, parent: usize) -> usize {
+        let log_visits = (self.nodes[parent].visits as f64).ln();
+        let uct = |i: usize| {
+            let node = &self.nodes[i];
+            node.wins / node.visits as f64
+                + self.config.exploration * (log_visits / node.visits as f64).sqrt()
+        };
+        let children = &self.nodes[parent].children;
+        let mut best = children[0];
+        let mut best_score = uct(best);
+        for &i in children.iter().skip(1) {
+            let score = uct(i);
+            if score > best_score {
+                best = i;
+                best_score = score;
+            }
+        }
+        best
+    }
+
+    // Plays `state` out and returns `Some(player_one_won)`, or `None` for a
+    // draw.
+    fn rollout(&mut self, mut state: GameState) -> Option<bool> {
+        for _ in 0..self.config.max_rollout_plies {
+            let children = state.legal_children();
+            if children.is_empty() {
+                return Some(!state.is_player_1);
+            }
+            let child = match self.config.rollout {
+                Rollout::Random => children[random_index(&mut self.rng, children.len())],
+                Rollout::Greedy => self.greedy_move(&children, state.is_player_1),
+            };
+            if child.winner.is_some() {
+                return child.winner;
+            }
+            state = GameState::new(child.board, !state.is_player_1);
+        }
+        None
+    }
+
+    fn greedy_move(&mut self, children: &[LegalMove], is_player_1: bool) -> LegalMove {
+        if let Some(child) = children
+            .iter()
+            .find(|child| child.winner == Some(is_player_1))
+        {
+            return *child;
+        }
+        let safe: Vec<&LegalMove> = children
+            .iter()
+            .filter(|child| child.winner.is_none())
+            .collect();
+        if safe.is_empty() {
+            return children[random_index(&mut self.rng, children.len())];
+        }
+        *safe[random_index(&mut self.rng, safe.len())]
+    }
+}
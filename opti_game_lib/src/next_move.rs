@@ -1,6 +1,6 @@
 use board::Board;
 use log::set_max_level;
-use piece::Piece;
+use piece::{Piece, CHICK_1, CHICK_2};
 use std::fmt::{Formatter, LowerHex, UpperHex};
 use std::ops::Add;
 use structs::Position;
@@ -29,6 +29,41 @@ impl NextMove {
         NextMove(piece_pos_pos)
     }
 
+    // Raw piece, old position and new position nibbles, not validated.
+    pub fn unpack(&self) -> (u8, u8, u8) {
+        (
+            ((self.0 & 0xf00) >> 8) as u8,
+            ((self.0 & 0x0f0) >> 4) as u8,
+            (self.0 & 0x00f) as u8,
+        )
+    }
+
+    pub fn piece(&self) -> Piece {
+        Piece::from((self.0 & 0xf00) >> 8)
+    }
+
+    pub fn old_position(&self) -> Position {
+        Position::from((self.0 & 0x0f0) >> 4)
+    }
+
+    pub fn new_position(&self) -> Position {
+        Position::from(self.0 & 0x00f)
+    }
+
+    pub fn is_drop(&self) -> bool {
+        self.old_position() == Position::Dead
+    }
+
+    // Whether this moves a chick onto its owner's far row.
+    pub fn is_promotion(&self) -> bool {
+        let piece = self.piece();
+        !self.is_drop()
+            && (piece == CHICK_1 || piece == CHICK_2)
+            && self
+                .new_position()
+                .is_winning_row_for_player(piece.is_mine(true))
+    }
+
     pub fn get_current_and_next(&self, is_player_1: bool) -> (u8, u8) {
         let mut new_position = Position::from(self.0 & 0x00f);
         let mut old_position = Position::from((self.0 & 0x0f0) >> 4);
@@ -0,0 +1,383 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use board::Board;
+use game_state::GameState;
+use next_move::NextMove;
+use piece::{
+    Piece, CHICK_1, CHICK_2, ELEPHANT_1, ELEPHANT_2, EMPTY, GIRAFFE_1, GIRAFFE_2, HEN_1, HEN_2,
+    LION_1, LION_2,
+};
+use structs::{GameError, Position};
+
+// Squares are written with a file letter and a rank number: `a1` is `X0Y0`
+// in player 1's corner, `c4` is `X2Y3`. A piece in hand is `*`.
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if *self == Position::Dead {
+            return write!(f, "*");
+        }
+        let (x, y) = <&Position as Into<(u8, u8)>>::into(self);
+        write!(f, "{}{}", (b'a' + x) as char, y + 1)
+    }
+}
+
+impl FromStr for Position {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "*" {
+            return Ok(Position::Dead);
+        }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file), Some(rank), None)
+                if file.is_ascii_lowercase() && rank.is_ascii_digit() =>
+            {
+                let x = file as u8 - b'a';
+                let y = (rank as u8).wrapping_sub(b'1');
+                if x < 3 && y < 4 {
+                    Ok(Position::from((x, y)))
+                } else {
+                    Err(GameError::OutOfBounds)
+                }
+            }
+            _ => Err(GameError::InvalidNotation),
+        }
+    }
+}
+
+// Boards are written like shogi SFEN: the rows from rank 4 down to rank 1
+// separated by `/`, digits standing for runs of empty squares, then the pieces
+// in hand or `-`. Player 1 pieces are lower case as in `Piece::show`, so the
+// initial board is `GLE/1C1/1c1/elg -`. A `GameState` adds the side to move,
+// `w` for player 1 and `b` for player 2, between the two: `GLE/1C1/1c1/elg w -`.
+fn rows_notation(board: &Board) -> String {
+    let mut s = String::new();
+    for (i, row) in board.get_state_processed().iter().rev().enumerate() {
+        if i > 0 {
+            s.push('/');
+        }
+        let mut empty = 0;
+        for piece in row.iter() {
+            if *piece == EMPTY {
+                empty += 1;
+                continue;
+            }
+            if empty > 0 {
+                s.push_str(&empty.to_string());
+                empty = 0;
+            }
+            s.push(piece.show());
+        }
+        if empty > 0 {
+            s.push_str(&empty.to_string());
+        }
+    }
+    s
+}
+
+fn hand_notation(board: &Board) -> String {
+    let mut hand: Vec<Piece> = board
+        .get_state()
+        .iter()
+        .filter(|(_, pos)| *pos == Position::Dead)
+        .map(|(piece, _)| *piece)
+        .collect();
+    // player 1 first, then by piece
+    hand.sort_by_key(|piece| (!piece.is_mine(true), piece.0));
+    if hand.is_empty() {
+        return "-".to_string();
+    }
+    hand.iter().map(|piece| piece.show()).collect()
+}
+
+// Checks that the pieces make up a Dobutsu set: one lion each on the board,
+// two elephants, two giraffes and two chicks or hens.
+fn is_complete_set(pieces: &[(Piece, Position)]) -> bool {
+    let count = |kinds: &[Piece]| pieces.iter().filter(|(p, _)| kinds.contains(p)).count();
+    pieces.len() == 8
+        && count(&[LION_1]) == 1
+        && count(&[LION_2]) == 1
+        && !pieces
+            .iter()
+            .any(|(p, pos)| (*p == LION_1 || *p == LION_2) && *pos == Position::Dead)
+        && count(&[ELEPHANT_1, ELEPHANT_2]) == 2
+        && count(&[GIRAFFE_1, GIRAFFE_2]) == 2
+        && count(&[CHICK_1, CHICK_2, HEN_1, HEN_2]) == 2
+}
+
+fn parse_board(rows: &str, hand: &str) -> Result<Board, GameError> {
+    let mut pieces: Vec<(Piece, Position)> = vec![];
+
+    let rows: Vec<&str> = rows.split('/').collect();
+    if rows.len() != 4 {
+        return Err(GameError::InvalidNotation);
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let y = 3 - i as u8;
+        let mut x = 0u8;
+        for c in row.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                x += empty as u8;
+            } else {
+                let piece = Piece::from_char(c).ok_or(GameError::InvalidNotation)?;
+                if x >= 3 {
+                    return Err(GameError::OutOfBounds);
+                }
+                pieces.push((piece, Position::from((x, y))));
+                x += 1;
+            }
+            if x > 3 {
+                return Err(GameError::OutOfBounds);
+            }
+        }
+        if x != 3 {
+            return Err(GameError::InvalidNotation);
+        }
+    }
+
+    if hand != "-" {
+        for c in hand.chars() {
+            let piece = Piece::from_char(c).ok_or(GameError::InvalidNotation)?;
+            // a hen is taken back as a chick
+            if piece == HEN_1 || piece == HEN_2 {
+                return Err(GameError::InvalidNotation);
+            }
+            pieces.push((piece, Position::Dead));
+        }
+    }
+
+    if !is_complete_set(&pieces) {
+        return Err(GameError::InvalidNotation);
+    }
+    let mut state = [(EMPTY, Position::Dead); 8];
+    state.copy_from_slice(&pieces);
+    let mut board = Board::new_empty();
+    board.put_state(state);
+    Ok(board)
+}
+
+fn parse_side(side: &str) -> Result<bool, GameError> {
+    match side {
+        "w" => Ok(true),
+        "b" => Ok(false),
+        _ => Err(GameError::InvalidNotation),
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", rows_notation(self), hand_notation(self))
+    }
+}
+
+// Also accepts the `GameState` form and ignores the side to move.
+impl FromStr for Board {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        match fields.len() {
+            2 => parse_board(fields[0], fields[1]),
+            3 => {
+                parse_side(fields[1])?;
+                parse_board(fields[0], fields[2])
+            }
+            _ => Err(GameError::InvalidNotation),
+        }
+    }
+}
+
+impl Display for GameState {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            rows_notation(&self.board),
+            if self.is_player_1 { "w" } else { "b" },
+            hand_notation(&self.board)
+        )
+    }
+}
+
+impl FromStr for GameState {
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err(GameError::InvalidNotation);
+        }
+        Ok(GameState::new(
+            parse_board(fields[0], fields[2])?,
+            parse_side(fields[1])?,
+        ))
+    }
+}
+
+// Moves are written as the piece, its square and the target square, like
+// `c b2-b3`, with `x` instead of `-` for a capture when the board is known,
+// and a trailing `+` when a chick promotes. Drops are written `C*a1`.
+impl Display for NextMove {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let piece = self.piece();
+        if self.is_drop() {
+            return write!(f, "{}*{}", piece, self.new_position());
+        }
+        write!(
+            f,
+            "{} {}-{}{}",
+            piece,
+            self.old_position(),
+            self.new_position(),
+            if self.is_promotion() { "+" } else { "" }
+        )
+    }
+}
+
+impl NextMove {
+    // Same as `Display`, with the capture marker for a move played on `board`.
+    pub fn notation(&self, board: &Board) -> String {
+        let (x, y) = <&Position as Into<(u8, u8)>>::into(&self.new_position());
+        if self.is_drop() || board.get_state_processed()[y as usize][x as usize] == EMPTY {
+            return self.to_string();
+        }
+        format!(
+            "{} {}x{}{}",
+            self.piece(),
+            self.old_position(),
+            self.new_position(),
+            if self.is_promotion() { "+" } else { "" }
+        )
+    }
+
+    // Reads a move typed by a player against the board it is played on and
+    // checks it with `Board::apply_move`. Spaces, the piece letter, the
+    // capture and promotion markers are optional and the letter's case does
+    // not matter: `c b2-b3`, `b2b3` and `C b2xb3` are the same move for
+    // player 1. A drop without a piece letter, `*a1`, is only accepted when
+    // all the pieces in hand are of the same kind.
+    pub fn parse(text: &str, board: &Board, is_player_1: bool) -> Result<NextMove, GameError> {
+        let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let text = text.trim_end_matches('+');
+
+        // a piece letter is never followed by a digit, a file letter always is
+        let second_is_digit = text.chars().nth(1).is_some_and(|c| c.is_ascii_digit());
+        let (kind, rest) = match text.chars().next() {
+            Some(c) if c != '*' && !second_is_digit => {
+                let kind = if is_player_1 {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                };
+                (
+                    Some(Piece::from_char(kind).ok_or(GameError::InavlidPiece)?),
+                    &text[c.len_utf8()..],
+                )
+            }
+            _ => (None, text),
+        };
+
+        let next_move = if let Some(to) = rest.strip_prefix('*') {
+            let to: Position = to.parse()?;
+            let mut hand: Vec<Piece> = board
+                .get_state()
+                .iter()
+                .filter(|(piece, pos)| *pos == Position::Dead && piece.is_mine(is_player_1))
+                .map(|(piece, _)| *piece)
+                .collect();
+            hand.dedup();
+            let piece = match kind {
+                _ if hand.is_empty() => return Err(GameError::EmptyCemetary),
+                Some(kind) if hand.contains(&kind) => kind,
+                Some(_) => return Err(GameError::PieceNotInBoard),
+                None if hand.len() == 1 => hand[0],
+                None => return Err(GameError::InvalidMove),
+            };
+            NextMove::new(piece, Position::Dead, to)
+        } else {
+            if rest.len() < 4 || !rest.is_char_boundary(2) {
+                return Err(GameError::InvalidNotation);
+            }
+            let from: Position = rest[..2].parse()?;
+            let to: Position = rest[2..].trim_start_matches(['-', 'x']).parse()?;
+            let (x, y) = <&Position as Into<(u8, u8)>>::into(&from);
+            let piece = board.get_state_processed()[y as usize][x as usize];
+            if piece == EMPTY {
+                return Err(GameError::PieceNotInBoard);
+            }
+            if kind.is_some_and(|kind| kind != piece) {
+                return Err(if piece.is_mine(is_player_1) {
+                    GameError::InvalidMove
+                } else {
+                    GameError::NotYourPiece
+                });
+            }
+            NextMove::new(piece, from, to)
+        };
+
+        board.apply_move(next_move, is_player_1)?;
+        Ok(next_move)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use board::Board;
+    use game_state::GameState;
+    use next_move::NextMove;
+    use structs::GameError;
+
+    #[test]
+    fn initial_board_round_trip() {
+        assert_eq!(Board::init().to_string(), "GLE/1C1/1c1/elg -");
+        assert_eq!("GLE/1C1/1c1/elg -".parse::<Board>().unwrap(), Board::init());
+        let state = GameState::init();
+        assert_eq!(state.to_string().parse::<GameState>().unwrap(), state);
+    }
+
+    #[test]
+    fn played_positions_round_trip() {
+        // the positions of the first three plies, with captures and drops
+        let mut states = vec![GameState::init()];
+        for _ in 0..3 {
+            states = states
+                .iter()
+                .flat_map(|state| state.children().unwrap_or_default())
+                .map(|(_, state)| state)
+                .collect();
+            for state in states.iter() {
+                let text = state.to_string();
+                assert_eq!(text.parse::<GameState>().unwrap(), *state, "{}", text);
+                assert_eq!(
+                    state.board.to_string().parse::<Board>().unwrap(),
+                    state.board
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hen_in_hand_is_rejected() {
+        assert!(matches!(
+            "GLE/3/1l1/eCg h".parse::<Board>(),
+            Err(GameError::InvalidNotation)
+        ));
+        assert!(matches!(
+            "GLE/3/1l1/eCg w H".parse::<GameState>(),
+            Err(GameError::InvalidNotation)
+        ));
+        assert!("GLE/3/1l1/eCg c".parse::<Board>().is_ok());
+    }
+
+    #[test]
+    fn move_round_trip() {
+        let state = GameState::init();
+        for next_move in state.legal_moves() {
+            let text = next_move.notation(&state.board);
+            let parsed = NextMove::parse(&text, &state.board, true).unwrap();
+            assert_eq!(parsed, next_move, "{}", text);
+        }
+    }
+}
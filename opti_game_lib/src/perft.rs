@@ -0,0 +1,177 @@
+use std::fmt::Display;
+use std::ops::AddAssign;
+
+use board::Board;
+use next_move::NextMove;
+use structs::{LegalMove, Position};
+
+/// Counters for the moves played at the last ply of a `perft` run.
+///
+/// A move ending the game has no children, so it only shows up in the counts
+/// of the depth it was played at.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub drops: u64,
+    pub promotions: u64,
+    // moves winning the game for the player making them
+    pub wins: u64,
+    // moves leaving the lion to be taken or otherwise losing on the spot
+    pub losses: u64,
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.drops += other.drops;
+        self.promotions += other.promotions;
+        self.wins += other.wins;
+        self.losses += other.losses;
+    }
+}
+
+impl Display for PerftStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "nodes: {}, captures: {}, drops: {}, promotions: {}, wins: {}, losses: {}",
+            self.nodes, self.captures, self.drops, self.promotions, self.wins, self.losses
+        )
+    }
+}
+
+fn dead_count(board: &Board) -> usize {
+    board
+        .0
+        .to_le_bytes()
+        .iter()
+        .filter(|b| *b & 0x0f == Position::Dead.as_u8())
+        .count()
+}
+
+fn leaf_stats(board: &Board, child: &LegalMove, is_player_1: bool) -> PerftStats {
+    let next_move = child.next_move;
+    PerftStats {
+        nodes: 1,
+        captures: (dead_count(&child.board) > dead_count(board)) as u64,
+        drops: next_move.is_drop() as u64,
+        promotions: next_move.is_promotion() as u64,
+        wins: (child.winner == Some(is_player_1)) as u64,
+        losses: (child.winner == Some(!is_player_1)) as u64,
+    }
+}
+
+fn perft_child(board: &Board, child: &LegalMove, is_player_1: bool, depth: u8) -> PerftStats {
+    if depth <= 1 {
+        return leaf_stats(board, child, is_player_1);
+    }
+    if child.winner.is_some() {
+        return PerftStats::default();
+    }
+    perft(child.board, !is_player_1, depth - 1)
+}
+
+// Walks every move path of `depth` plies from `board`, `is_player_1` to move.
+pub fn perft(board: Board, is_player_1: bool, depth: u8) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth == 0 {
+        stats.nodes = 1;
+        return stats;
+    }
+    for child in board.legal_children(is_player_1) {
+        stats += perft_child(&board, &child, is_player_1, depth);
+    }
+    stats
+}
+
+// `perft` split by first move.
+pub fn perft_divide(board: Board, is_player_1: bool, depth: u8) -> Vec<(NextMove, PerftStats)> {
+    if depth == 0 {
+        return vec![];
+    }
+    board
+        .legal_children(is_player_1)
+        .iter()
+        .map(|child| {
+            (
+                child.next_move,
+                perft_child(&board, child, is_player_1, depth),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use board::Board;
+    use perft::{perft, perft_divide};
+    use structs::GameResult;
+
+    // Move paths from the initial position when a game ends as soon as a lion
+    // is taken, left where it can be taken, or safe on the far row. These are
+    // not published counts: they come from this code and only catch changes
+    // to the rules engine.
+    const INITIAL_NODES: [u64; 6] = [4, 17, 96, 577, 3199, 18754];
+
+    #[test]
+    fn initial_position_node_counts() {
+        for (depth, nodes) in INITIAL_NODES.iter().enumerate() {
+            let stats = perft(Board::init(), true, depth as u8 + 1);
+            assert_eq!(stats.nodes, *nodes, "depth {}", depth + 1);
+        }
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let total: u64 = perft_divide(Board::init(), true, 4)
+            .iter()
+            .map(|(_, stats)| stats.nodes)
+            .sum();
+        assert_eq!(total, INITIAL_NODES[3]);
+    }
+
+    // `get_next_states_2`, which `generate` uses, stops at a winning move and
+    // leaves out the moves losing on the spot; otherwise it must give the
+    // same children as `legal_children`.
+    #[test]
+    fn generators_agree() {
+        let mut boards = vec![(Board::init(), true)];
+        for _ in 0..5 {
+            let mut next = vec![];
+            for (board, is_player_1) in boards.iter() {
+                let children = board.legal_children(*is_player_1);
+                let wins = children
+                    .iter()
+                    .any(|child| child.winner == Some(*is_player_1));
+                let mut expected: Vec<(u16, u64)> = children
+                    .iter()
+                    .filter(|child| child.winner.is_none())
+                    .map(|child| (child.next_move.0, child.board.0))
+                    .collect();
+                match board.get_next_states_2(*is_player_1) {
+                    GameResult::WhiteWin => assert!(wins && *is_player_1),
+                    GameResult::BlackWin => assert!(wins && !*is_player_1),
+                    GameResult::Intermediate(found) => {
+                        assert!(!wins, "{:X}", board.0);
+                        let mut found: Vec<(u16, u64)> = found
+                            .iter()
+                            .map(|(next_move, board)| (next_move.0, board.0))
+                            .collect();
+                        found.sort_unstable();
+                        expected.sort_unstable();
+                        assert_eq!(found, expected, "{:X}", board.0);
+                    }
+                }
+                next.extend(
+                    children
+                        .iter()
+                        .filter(|child| child.winner.is_none())
+                        .map(|child| (child.board, !*is_player_1)),
+                );
+            }
+            boards = next;
+        }
+    }
+}
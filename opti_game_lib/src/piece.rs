@@ -78,6 +78,23 @@ impl Piece {
         s
     }
 
+    // Inverse of `show`.
+    pub fn from_char(c: char) -> Option<Piece> {
+        match c {
+            'l' => Some(LION_1),
+            'L' => Some(LION_2),
+            'e' => Some(ELEPHANT_1),
+            'E' => Some(ELEPHANT_2),
+            'g' => Some(GIRAFFE_1),
+            'G' => Some(GIRAFFE_2),
+            'c' => Some(CHICK_1),
+            'C' => Some(CHICK_2),
+            'h' => Some(HEN_1),
+            'H' => Some(HEN_2),
+            _ => None,
+        }
+    }
+
     pub(crate) fn moves(&self) -> &'static [&'static Move] {
         match *self {
             LION_1 | LION_2 => MOVE_LION,
@@ -0,0 +1,346 @@
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use structs::GameResult;
+
+// Progress of a long job, such as `generate` or `solve`, run as a list of
+// stages: a phase ("generate", "white", "length", ...) and a depth within it.
+// Each stage counts the positions it handles and, when it sees them, how
+// their games end. A running stage is logged every `LOG_INTERVAL`, each stage
+// when it finishes, and the whole run can be written as JSON.
+
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+// positions between two looks at the clock
+const CLOCK_STRIDE: u64 = 1 << 16;
+
+#[derive(Debug, Clone)]
+pub struct Stage {
+    pub phase: &'static str,
+    pub depth: u32,
+    pub positions: u64,
+    pub white_wins: u64,
+    pub black_wins: u64,
+    pub intermediate: u64,
+    // bytes kept in memory for each position of the stage
+    pub position_size: u64,
+    pub seconds: f64,
+}
+
+impl Stage {
+    pub fn rate(&self) -> f64 {
+        rate(self.positions, self.seconds)
+    }
+}
+
+fn rate(positions: u64, seconds: f64) -> f64 {
+    if seconds > 0.0 {
+        positions as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+pub struct Progress {
+    job: String,
+    start: Instant,
+    stage_start: Instant,
+    last_log: Instant,
+    // (key, value) pairs describing the run, for the summary
+    notes: Vec<(&'static str, String)>,
+    stages: Vec<Stage>,
+    current: Option<Stage>,
+    // positions the whole job is expected to handle
+    expected: Option<u64>,
+    // last depth of the current phase
+    last_depth: Option<u32>,
+}
+
+impl Progress {
+    pub fn new(job: &str) -> Progress {
+        let now = Instant::now();
+        Progress {
+            job: job.to_string(),
+            start: now,
+            stage_start: now,
+            last_log: now,
+            notes: vec![],
+            stages: vec![],
+            current: None,
+            expected: None,
+            last_depth: None,
+        }
+    }
+
+    pub fn note(&mut self, key: &'static str, value: &str) {
+        self.notes.push((key, value.to_string()));
+    }
+
+    // The ETA is the time left to reach `positions` at the average rate.
+    pub fn expect(&mut self, positions: u64) {
+        self.expected = Some(positions);
+    }
+
+    // Without an expected count, the ETA assumes the current phase goes up to
+    // `depth`, each depth growing as much as the previous one did.
+    pub fn expect_depth(&mut self, depth: u32) {
+        self.last_depth = Some(depth);
+    }
+
+    // Starts a stage, finishing the running one.
+    pub fn start(&mut self, phase: &'static str, depth: u32, position_size: u64) {
+        self.finish();
+        self.stage_start = Instant::now();
+        self.current = Some(Stage {
+            phase,
+            depth,
+            positions: 0,
+            white_wins: 0,
+            black_wins: 0,
+            intermediate: 0,
+            position_size,
+            seconds: 0.0,
+        });
+    }
+
+    pub fn record(&mut self, game_result: &GameResult) {
+        match game_result {
+            GameResult::WhiteWin => self.add_outcomes(1, 0, 0),
+            GameResult::BlackWin => self.add_outcomes(0, 1, 0),
+            GameResult::Intermediate(_) => self.add_outcomes(0, 0, 1),
+        }
+    }
+
+    pub fn add_outcomes(&mut self, white_wins: u64, black_wins: u64, intermediate: u64) {
+        if let Some(stage) = self.current.as_mut() {
+            stage.white_wins += white_wins;
+            stage.black_wins += black_wins;
+            stage.intermediate += intermediate;
+        }
+        self.add(white_wins + black_wins + intermediate);
+    }
+
+    // Counts `positions` whose outcome is not known.
+    pub fn add(&mut self, positions: u64) {
+        let stage = match self.current.as_mut() {
+            Some(stage) => stage,
+            None => return,
+        };
+        let before = stage.positions;
+        stage.positions += positions;
+        if before / CLOCK_STRIDE != stage.positions / CLOCK_STRIDE
+            && self.last_log.elapsed() >= LOG_INTERVAL
+        {
+            self.last_log = Instant::now();
+            info!("{}", self.status());
+        }
+    }
+
+    // Finishes the running stage, if any, and logs it.
+    pub fn finish(&mut self) {
+        let mut stage = match self.current.take() {
+            Some(stage) => stage,
+            None => return,
+        };
+        stage.seconds = self.stage_start.elapsed().as_secs_f64();
+        self.stages.push(stage);
+        info!("{}", self.status());
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    pub fn positions(&self) -> u64 {
+        self.stages
+            .iter()
+            .chain(self.current.iter())
+            .map(|stage| stage.positions)
+            .sum()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    // Positions per second since the start.
+    pub fn rate(&self) -> f64 {
+        rate(self.positions(), self.elapsed().as_secs_f64())
+    }
+
+    pub fn memory_estimate(&self) -> u64 {
+        self.stages
+            .iter()
+            .chain(self.current.iter())
+            .map(|stage| stage.positions * stage.position_size)
+            .sum()
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        if rate <= 0.0 {
+            return None;
+        }
+        let left = match self.expected {
+            Some(expected) => expected.saturating_sub(self.positions()) as f64,
+            None => self.projected_positions()?,
+        };
+        Some(Duration::from_secs_f64(left / rate))
+    }
+
+    // Positions left in the current phase up to `last_depth`, extrapolated
+    // from the growth between its last two finished depths.
+    fn projected_positions(&self) -> Option<f64> {
+        let last_depth = self.last_depth?;
+        let phase = match &self.current {
+            Some(stage) => stage.phase,
+            None => self.stages.last()?.phase,
+        };
+        let mut done = self
+            .stages
+            .iter()
+            .rev()
+            .take_while(|stage| stage.phase == phase);
+        let last = done.next()?;
+        let growth = match done.next() {
+            Some(before) if before.positions > 0 => last.positions as f64 / before.positions as f64,
+            _ => return None,
+        };
+        let running = self.current.as_ref().map_or(0, |stage| stage.positions);
+        let mut size = last.positions as f64;
+        let mut left = 0.0;
+        for depth in last.depth + 1..=last_depth {
+            size *= growth;
+            left += size;
+            if self
+                .current
+                .as_ref()
+                .is_some_and(|stage| stage.depth == depth)
+            {
+                left -= (running as f64).min(size);
+            }
+        }
+        Some(left)
+    }
+
+    // One line on the running stage, or the last one finished.
+    pub fn status(&self) -> String {
+        let (stage, seconds) = match &self.current {
+            Some(stage) => (stage, self.stage_start.elapsed().as_secs_f64()),
+            None => match self.stages.last() {
+                Some(stage) => (stage, stage.seconds),
+                None => return format!("{}: nothing done", self.job),
+            },
+        };
+        let mut status = format!(
+            "{} {}: {} positions",
+            stage.phase, stage.depth, stage.positions
+        );
+        if stage.white_wins + stage.black_wins + stage.intermediate > 0 {
+            let _ = write!(
+                status,
+                " (white wins {}, black wins {}, intermediate {})",
+                stage.white_wins, stage.black_wins, stage.intermediate
+            );
+        }
+        let _ = write!(
+            status,
+            ", {:.0}/s, {} in total, ~{} MiB",
+            rate(stage.positions, seconds),
+            self.positions(),
+            self.memory_estimate() >> 20
+        );
+        if let Some(eta) = self.eta() {
+            let _ = write!(status, ", ETA {}", format_duration(eta));
+        }
+        status
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        json.push_str("{\n");
+        let _ = writeln!(json, "  \"job\": {},", json_string(&self.job));
+        for (key, value) in self.notes.iter() {
+            let _ = writeln!(json, "  {}: {},", json_string(key), json_string(value));
+        }
+        let _ = writeln!(json, "  \"seconds\": {:.3},", self.elapsed().as_secs_f64());
+        let _ = writeln!(json, "  \"positions\": {},", self.positions());
+        let _ = writeln!(json, "  \"positions_per_second\": {:.1},", self.rate());
+        let _ = writeln!(
+            json,
+            "  \"memory_estimate_bytes\": {},",
+            self.memory_estimate()
+        );
+        json.push_str("  \"stages\": [");
+        for (i, stage) in self.stages.iter().chain(self.current.iter()).enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "\n    {{\"phase\": {}, \"depth\": {}, \"positions\": {}, \
+                 \"white_wins\": {}, \"black_wins\": {}, \"intermediate\": {}, \
+                 \"seconds\": {:.3}, \"positions_per_second\": {:.1}}}",
+                json_string(stage.phase),
+                stage.depth,
+                stage.positions,
+                stage.white_wins,
+                stage.black_wins,
+                stage.intermediate,
+                stage.seconds,
+                stage.rate()
+            );
+        }
+        if !self.stages.is_empty() || self.current.is_some() {
+            json.push_str("\n  ");
+        }
+        json.push_str("]\n}\n");
+        json
+    }
+
+    // Writes `to_json` to `path`, through a temporary file so that a summary
+    // is never seen half written.
+    pub fn write_summary<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        {
+            let mut f = BufWriter::new(File::create(&tmp)?);
+            f.write_all(self.to_json().as_bytes())?;
+            f.flush()?;
+        }
+        fs::rename(&tmp, path)
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
@@ -0,0 +1,247 @@
+use board::Board;
+use game_state::GameState;
+use piece::{Piece, CHICK_1, ELEPHANT_1, EMPTY, GIRAFFE_1, LION_1, LION_2};
+use structs::Position;
+
+// Dense numbering of positions: `rank` maps every position with a complete
+// set of pieces (one lion each on the board, two elephants, two giraffes and
+// two chicks or hens anywhere, promoted or not, and the side to move) to a
+// distinct integer below `position_count()`, and `unrank` maps it back.
+//
+// The index is a mixed-radix number, most significant first: the side to
+// move, the two lions' squares, then for the elephants, the giraffes and the
+// chicks in turn how many of the pair are on the board, which of the squares
+// still free they stand on, their colours (and promotion for chicks) in square
+// order, and the multiset of the ones in hand. The number of ways to place
+// the kinds after one depends on how many squares it took, so each kind's
+// choices are laid out in blocks sized by `completions`.
+
+// First piece id of each kind and the number of variants of it on the board
+// (colours, and promotion for chicks) and in hand, where a hen is a chick.
+const KINDS: [(u8, u8, u8); 3] = [(ELEPHANT_1.0, 2, 2), (GIRAFFE_1.0, 2, 2), (CHICK_1.0, 4, 2)];
+
+fn binomial(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |acc, i| acc * (n - i) / (i + 1))
+}
+
+// Multisets of `size` elements out of `alphabet`.
+fn multisets(alphabet: u64, size: u64) -> u64 {
+    binomial(alphabet + size - 1, size)
+}
+
+// Number of ways to place kinds `kind..` with `free` empty squares left.
+fn completions(kind: usize, free: u64) -> u64 {
+    if kind == KINDS.len() {
+        return 1;
+    }
+    (0..=2).map(|k| block(kind, free, k)).sum()
+}
+
+// Number of ways to place kinds `kind..` with `on_board` pieces of `kind` on
+// the board.
+fn block(kind: usize, free: u64, on_board: u64) -> u64 {
+    if on_board > free {
+        return 0;
+    }
+    let (_, alphabet, hand_alphabet) = KINDS[kind];
+    binomial(free, on_board)
+        * (alphabet as u64).pow(on_board as u32)
+        * multisets(hand_alphabet as u64, 2 - on_board)
+        * completions(kind + 1, free - on_board)
+}
+
+pub fn position_count() -> u64 {
+    2 * 12 * 11 * completions(0, 10)
+}
+
+// `state.board` must hold a complete set of pieces.
+pub fn rank(state: &GameState) -> u64 {
+    let pieces = state.board.get_state();
+    let square = |piece: Piece| {
+        pieces
+            .iter()
+            .find(|(p, _)| *p == piece)
+            .map(|(_, pos)| pos.as_u8())
+            .unwrap() as u64
+    };
+    let lion_1 = square(LION_1);
+    let lion_2 = square(LION_2);
+    let lions = (!state.is_player_1 as u64) * 132 + lion_1 * 11 + lion_2 - (lion_2 > lion_1) as u64;
+    let mut index = lions * completions(0, 10);
+
+    let mut free: Vec<u8> = (0..12)
+        .filter(|s| *s as u64 != lion_1 && *s as u64 != lion_2)
+        .collect();
+    for (kind, &(first, alphabet, hand_alphabet)) in KINDS.iter().enumerate() {
+        let alphabet = alphabet as u64;
+        let hand_alphabet = hand_alphabet as u64;
+        let mut on_board: Vec<(u8, u64)> = vec![];
+        let mut in_hand: Vec<u64> = vec![];
+        for (piece, pos) in pieces.iter() {
+            if piece.0 < first || piece.0 >= first + alphabet as u8 {
+                continue;
+            }
+            let variant = (piece.0 - first) as u64;
+            if *pos == Position::Dead {
+                debug_assert!(variant < hand_alphabet);
+                in_hand.push(variant);
+            } else {
+                on_board.push((pos.as_u8(), variant));
+            }
+        }
+        debug_assert_eq!(on_board.len() + in_hand.len(), 2);
+        on_board.sort();
+        in_hand.sort();
+        let k = on_board.len() as u64;
+        let f = free.len() as u64;
+
+        // squares, as a combination of indices into `free`
+        let mut combination = 0;
+        let mut variants = 0;
+        for (i, (square, variant)) in on_board.iter().enumerate() {
+            let c = free.iter().position(|s| s == square).unwrap() as u64;
+            combination += binomial(c, i as u64 + 1);
+            variants = variants * alphabet + variant;
+        }
+        let hand = match in_hand[..] {
+            [] => 0,
+            [a] => a,
+            [a, b] => b * (b + 1) / 2 + a,
+            _ => unreachable!(),
+        };
+
+        let offset: u64 = (0..k).map(|j| block(kind, f, j)).sum();
+        let inner = (combination * alphabet.pow(k as u32) + variants)
+            * multisets(hand_alphabet, 2 - k)
+            + hand;
+        index += offset + inner * completions(kind + 1, f - k);
+        free.retain(|s| !on_board.iter().any(|(square, _)| square == s));
+    }
+    index
+}
+
+pub fn unrank(index: u64) -> GameState {
+    debug_assert!(index < position_count());
+    let per_lions = completions(0, 10);
+    let lions = index / per_lions;
+    let mut index = index % per_lions;
+    let is_player_1 = lions < 132;
+    let lion_1 = (lions % 132 / 11) as u8;
+    let lion_2 = (lions % 11) as u8;
+    let lion_2 = lion_2 + (lion_2 >= lion_1) as u8;
+
+    let mut pieces = vec![
+        (LION_1, Position::from(lion_1)),
+        (LION_2, Position::from(lion_2)),
+    ];
+    let mut free: Vec<u8> = (0..12).filter(|s| *s != lion_1 && *s != lion_2).collect();
+    for (kind, &(first, alphabet, hand_alphabet)) in KINDS.iter().enumerate() {
+        let alphabet = alphabet as u64;
+        let f = free.len() as u64;
+        let mut k = 0;
+        while index >= block(kind, f, k) {
+            index -= block(kind, f, k);
+            k += 1;
+        }
+        let rest = completions(kind + 1, f - k);
+        let mut inner = index / rest;
+        index %= rest;
+
+        let hand_count = multisets(hand_alphabet as u64, 2 - k);
+        let hand = inner % hand_count;
+        inner /= hand_count;
+        let mut variants = inner % alphabet.pow(k as u32);
+        let mut combination = inner / alphabet.pow(k as u32);
+
+        let mut squares = vec![];
+        for i in (1..=k).rev() {
+            let mut c = i - 1;
+            while binomial(c + 1, i) <= combination {
+                c += 1;
+            }
+            combination -= binomial(c, i);
+            squares.push(free[c as usize]);
+        }
+        squares.reverse();
+        let mut on_board = vec![];
+        for square in squares.iter().rev() {
+            on_board.push((
+                Piece(first + (variants % alphabet) as u8),
+                Position::from(*square),
+            ));
+            variants /= alphabet;
+        }
+        pieces.extend(on_board);
+
+        let in_hand: Vec<u64> = match 2 - k {
+            0 => vec![],
+            1 => vec![hand],
+            _ => {
+                let mut b = 0;
+                while (b + 1) * (b + 2) / 2 <= hand {
+                    b += 1;
+                }
+                vec![hand - b * (b + 1) / 2, b]
+            }
+        };
+        for variant in in_hand {
+            pieces.push((Piece(first + variant as u8), Position::Dead));
+        }
+        free.retain(|s| !squares.contains(s));
+    }
+
+    let mut state = [(EMPTY, Position::Dead); 8];
+    state.copy_from_slice(&pieces);
+    let mut board = Board::new_empty();
+    board.put_state(state);
+    GameState::new(board, is_player_1)
+}
+
+#[cfg(test)]
+mod tests {
+    use game_state::GameState;
+    use piece::{HEN_1, HEN_2};
+    use rank::{position_count, rank, unrank};
+    use structs::Position;
+
+    #[test]
+    fn every_index_is_a_position() {
+        // a prime stride, to go through every part of the mixed radix
+        for index in (0..position_count()).step_by(1_000_003) {
+            let state = unrank(index);
+            assert!(
+                !state
+                    .board
+                    .get_state()
+                    .iter()
+                    .any(|(piece, pos)| *pos == Position::Dead
+                        && (*piece == HEN_1 || *piece == HEN_2))
+            );
+            assert_eq!(rank(&state), index);
+        }
+    }
+
+    #[test]
+    fn played_positions_round_trip() {
+        let mut states = vec![GameState::init()];
+        for _ in 0..4 {
+            let mut next = vec![];
+            for state in states.iter() {
+                for child in state.legal_children() {
+                    if child.winner.is_none() {
+                        next.push(GameState::new(child.board, !state.is_player_1));
+                    }
+                }
+            }
+            states = next;
+        }
+        for state in states.iter() {
+            let index = rank(state);
+            assert!(index < position_count());
+            assert_eq!(unrank(index), *state);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use game_state::GameState;
+use history::{DrawRule, GameHistory};
+use next_move::NextMove;
+use structs::{GameError, GameOutcome};
+
+/// A played game: free-form headers, the start position, the moves and how
+/// it ended.
+///
+/// The text form is a block of `[Key "Value"]` header lines, a blank line,
+/// then one numbered move per line in the notation of `NextMove::notation`:
+///
+/// ```text
+/// [Event "Casual game"]
+/// [Start "GLE/1C1/1c1/elg w -"]
+/// [Result "1-0"]
+/// [Termination "lion captured"]
+///
+/// 1. c b2xb3
+/// 2. L b4-a3
+/// ```
+///
+/// `Start`, `Result` and `Termination` are derived from the game itself and
+/// are not kept in `headers`.
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub headers: Vec<(String, String)>,
+    pub start: GameState,
+    pub moves: Vec<NextMove>,
+    pub outcome: Option<GameOutcome>,
+}
+
+/// Where and why a game record could not be read. `ply` is 0 for errors in
+/// the headers, otherwise the 1-based number of the offending move. `line` is
+/// 0 when the record did not come from text.
+#[derive(Debug)]
+pub struct RecordError {
+    pub line: usize,
+    pub ply: usize,
+    pub error: RecordErrorKind,
+}
+
+#[derive(Debug)]
+pub enum RecordErrorKind {
+    Game(GameError),
+    // a `Result` or `Termination` header saying otherwise than the moves
+    Header {
+        key: &'static str,
+        stored: String,
+        replayed: String,
+    },
+}
+
+impl From<GameError> for RecordErrorKind {
+    fn from(error: GameError) -> Self {
+        RecordErrorKind::Game(error)
+    }
+}
+
+impl Display for RecordErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            RecordErrorKind::Game(error) => write!(f, "{}", error),
+            RecordErrorKind::Header {
+                key,
+                stored,
+                replayed,
+            } => write!(
+                f,
+                "{} is \"{}\" but the moves give \"{}\"",
+                key, stored, replayed
+            ),
+        }
+    }
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.ply == 0 {
+            write!(f, "line {}: {}", self.line, self.error)
+        } else {
+            write!(f, "line {}, ply {}: {}", self.line, self.ply, self.error)
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+fn result_notation(outcome: Option<GameOutcome>) -> &'static str {
+    match outcome.map(|o| o.winner) {
+        Some(Some(true)) => "1-0",
+        Some(Some(false)) => "0-1",
+        Some(None) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+impl GameRecord {
+    pub fn new(start: GameState) -> GameRecord {
+        GameRecord {
+            headers: vec![],
+            start,
+            moves: vec![],
+            outcome: None,
+        }
+    }
+
+    pub fn from_history(history: &GameHistory) -> GameRecord {
+        GameRecord {
+            headers: vec![],
+            start: history.start(),
+            moves: history.moves().to_vec(),
+            outcome: history.outcome(),
+        }
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        match self.headers.iter_mut().find(|(k, _)| k == key) {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    // Plays the moves again from the start position.
+    pub fn replay(&self, rule: DrawRule) -> Result<GameHistory, RecordError> {
+        let mut history = GameHistory::with_rule(self.start, rule);
+        for (i, next_move) in self.moves.iter().enumerate() {
+            history.play(*next_move).map_err(|error| RecordError {
+                line: 0,
+                ply: i + 1,
+                error: error.into(),
+            })?;
+        }
+        Ok(history)
+    }
+
+    pub fn parse(text: &str) -> Result<GameRecord, RecordError> {
+        GameRecord::parse_with_rule(text, DrawRule::default())
+    }
+
+    // Reads a record and checks every move with the rules engine, reporting
+    // the first one that cannot be played, then checks the `Result` and
+    // `Termination` headers against how the moves end the game.
+    pub fn parse_with_rule(text: &str, rule: DrawRule) -> Result<GameRecord, RecordError> {
+        let mut headers = vec![];
+        let mut start = GameState::init();
+        let mut history: Option<GameHistory> = None;
+        // the headers with their line
+        let mut result: Option<(usize, String)> = None;
+        let mut termination: Option<(usize, String)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                if history.is_some() {
+                    return Err(RecordError {
+                        line: line_number,
+                        ply: 0,
+                        error: GameError::InvalidNotation.into(),
+                    });
+                }
+                let (key, value) = parse_header(line).ok_or(RecordError {
+                    line: line_number,
+                    ply: 0,
+                    error: GameError::InvalidNotation.into(),
+                })?;
+                match key {
+                    "Start" => {
+                        start = value.parse().map_err(|error: GameError| RecordError {
+                            line: line_number,
+                            ply: 0,
+                            error: error.into(),
+                        })?
+                    }
+                    "Result" => result = Some((line_number, value.to_string())),
+                    "Termination" => termination = Some((line_number, value.to_string())),
+                    _ => headers.push((key.to_string(), value.to_string())),
+                }
+                continue;
+            }
+
+            let history = history.get_or_insert_with(|| GameHistory::with_rule(start, rule));
+            let ply = history.moves().len() + 1;
+            let text = match line.split_once('.') {
+                Some((number, rest)) if number.trim().parse::<usize>().is_ok() => rest,
+                _ => line,
+            };
+            let current = history.current();
+            NextMove::parse(text, &current.board, current.is_player_1)
+                .and_then(|next_move| history.play(next_move))
+                .map_err(|error| RecordError {
+                    line: line_number,
+                    ply,
+                    error: error.into(),
+                })?;
+        }
+
+        let history = history.unwrap_or_else(|| GameHistory::with_rule(start, rule));
+        let outcome = history.outcome();
+        let replayed = [
+            ("Result", result, result_notation(outcome).to_string()),
+            (
+                "Termination",
+                termination,
+                outcome.map_or(String::new(), |o| o.termination.to_string()),
+            ),
+        ];
+        for (key, header, replayed) in replayed {
+            if let Some((line, stored)) = header.filter(|(_, stored)| *stored != replayed) {
+                return Err(RecordError {
+                    line,
+                    ply: 0,
+                    error: RecordErrorKind::Header {
+                        key,
+                        stored,
+                        replayed,
+                    },
+                });
+            }
+        }
+        Ok(GameRecord {
+            headers,
+            ..GameRecord::from_history(&history)
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<GameRecord, Box<dyn Error>> {
+        Ok(GameRecord::parse(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+fn parse_header(line: &str) -> Option<(&str, &str)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (key, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((key, value))
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        for (key, value) in self.headers.iter() {
+            writeln!(f, "[{} \"{}\"]", key, value)?;
+        }
+        writeln!(f, "[Start \"{}\"]", self.start)?;
+        writeln!(f, "[Result \"{}\"]", result_notation(self.outcome))?;
+        if let Some(outcome) = self.outcome {
+            writeln!(f, "[Termination \"{}\"]", outcome.termination)?;
+        }
+        writeln!(f)?;
+        let mut state = self.start;
+        for (i, next_move) in self.moves.iter().enumerate() {
+            writeln!(f, "{}. {}", i + 1, next_move.notation(&state.board))?;
+            state = state.compute_child(*next_move);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_state::GameState;
+    use history::GameHistory;
+    use next_move::NextMove;
+    use record::{GameRecord, RecordErrorKind};
+
+    // White takes the chick, which then attacks Black's lion, and Black
+    // leaves the lion there.
+    fn won_game() -> GameRecord {
+        let mut history = GameHistory::new(GameState::init());
+        for text in ["b2b3", "a4a3"] {
+            let current = history.current();
+            let next_move = NextMove::parse(text, &current.board, current.is_player_1).unwrap();
+            history.play(next_move).unwrap();
+        }
+        assert!(history.outcome().is_some());
+        GameRecord::from_history(&history)
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let record = won_game();
+        let parsed = GameRecord::parse(&record.to_string()).unwrap();
+        assert_eq!(parsed.moves, record.moves);
+        assert_eq!(parsed.outcome, record.outcome);
+    }
+
+    #[test]
+    fn wrong_result_is_rejected() {
+        let text = won_game()
+            .to_string()
+            .replace("[Result \"1-0\"]", "[Result \"0-1\"]");
+        let error = GameRecord::parse(&text).unwrap_err();
+        assert!(matches!(
+            error.error,
+            RecordErrorKind::Header { key: "Result", .. }
+        ));
+        assert_eq!(error.line, 2);
+    }
+
+    #[test]
+    fn result_of_unfinished_game_is_rejected() {
+        let text = "[Result \"1-0\"]\n\n1. c b2xb3\n";
+        assert!(GameRecord::parse(text).is_err());
+        assert!(GameRecord::parse("[Result \"*\"]\n\n1. c b2xb3\n").is_ok());
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::mem::size_of;
+
+use log::info;
+
+use game_state::GameState;
+use next_move::NextMove;
+use progress::Progress;
+use rank::{rank, unrank};
+use structs::LegalMove;
+
+/// Exact value of a position for the side to move, with the number of plies
+/// until the game ends under perfect play: the winner ends it as fast as
+/// possible, the loser holds on as long as possible.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+
+impl Value {
+    // Value of the parent position when this is the value of one of its
+    // children, which the opponent moves in.
+    pub fn parent(self) -> Value {
+        match self {
+            Value::Win(n) => Value::Loss(n + 1),
+            Value::Loss(n) => Value::Win(n + 1),
+            Value::Draw => Value::Draw,
+        }
+    }
+
+    // Whether the side to move prefers `self` to `other`.
+    pub fn is_better_than(self, other: Value) -> bool {
+        match (self, other) {
+            (Value::Win(a), Value::Win(b)) => a < b,
+            (Value::Win(_), _) => true,
+            (Value::Draw, Value::Loss(_)) => true,
+            (Value::Loss(a), Value::Loss(b)) => a > b,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Win(n) => write!(f, "W{}", n),
+            Value::Loss(n) => write!(f, "L{}", n),
+            Value::Draw => write!(f, "D"),
+        }
+    }
+}
+
+/// Every position reachable from a root with its exact value, built by
+/// `solve`. Only canonical positions (see `GameState::canonical`) are stored,
+/// by their `rank`; the others are looked up through theirs.
+pub struct RetrogradeTable {
+    ranks: Vec<u64>,
+    index: HashMap<u64, u32>,
+    values: Vec<Value>,
+}
+
+// Value of a move ending the game with `winner` for the player making it,
+// `None` if the game goes on.
+pub fn terminal_value(winner: Option<bool>, is_player_1: bool) -> Option<Value> {
+    match winner {
+        Some(player_one_won) if player_one_won == is_player_1 => Some(Value::Win(1)),
+        // the lion is left hanging and gets taken on the next ply
+        Some(_) => Some(Value::Loss(2)),
+        None => None,
+    }
+}
+
+// Value of a move for the player making it, if it is known yet.
+fn move_value(table: &RetrogradeTable, child: &LegalMove, is_player_1: bool) -> Option<Value> {
+    terminal_value(child.winner, is_player_1).or_else(|| {
+        table
+            .value(&GameState::new(child.board, !is_player_1))
+            .map(Value::parent)
+    })
+}
+
+impl RetrogradeTable {
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    pub fn value(&self, state: &GameState) -> Option<Value> {
+        let (state, _) = state.canonical();
+        self.index
+            .get(&rank(&state))
+            .map(|i| self.values[*i as usize])
+    }
+
+    // A move reaching `value(state)` in `state`'s own orientation, `None` if
+    // there is no legal move.
+    pub fn best_move(&self, state: &GameState) -> Option<NextMove> {
+        let mut best: Option<(NextMove, Value)> = None;
+        for child in state.legal_children() {
+            if let Some(value) = move_value(self, &child, state.is_player_1) {
+                if best.is_none_or(|(_, b)| value.is_better_than(b)) {
+                    best = Some((child.next_move, value));
+                }
+            }
+        }
+        best.map(|(next_move, _)| next_move)
+    }
+
+    // Canonical positions only.
+    pub fn iter(&self) -> impl Iterator<Item = (GameState, Value)> + '_ {
+        self.ranks
+            .iter()
+            .zip(self.values.iter())
+            .map(|(rank, value)| (unrank(*rank), *value))
+    }
+}
+
+// Enumerates every position reachable from `root`, up to symmetry, and
+// solves them by retrograde analysis.
+//
+// The enumeration counts the moves of each position whose value is not known
+// from the move alone and keeps, for each position, the positions moving to
+// it. Terminal moves are labelled with `has_winner` through `legal_children`.
+// Values are then settled in order of their length: a position whose child
+// is lost in `n` plies is won in `n + 1`, and a position whose last unsettled
+// child is won is lost in one more ply than its longest such child. Positions
+// never settled can only go around in cycles and are draws.
+//
+// The enumeration, each length and the draws are stages of `progress`.
+pub fn solve(root: GameState, progress: &mut Progress) -> RetrogradeTable {
+    solve_with(root, progress, |state| state.legal_children())
+}
+
+// `solve` over the moves given by `children`, which must be symmetric.
+fn solve_with<F>(root: GameState, progress: &mut Progress, children: F) -> RetrogradeTable
+where
+    F: Fn(&GameState) -> Vec<LegalMove>,
+{
+    let mut table = RetrogradeTable {
+        ranks: vec![],
+        index: HashMap::new(),
+        values: vec![],
+    };
+    let mut values: Vec<Option<Value>> = vec![];
+    // moves whose value is still unknown, then the longest loss among the
+    // known ones
+    let mut unsettled: Vec<u16> = vec![];
+    let mut longest_loss: Vec<u16> = vec![];
+    // (child, parent) for every move between two positions of the table
+    let mut edges: Vec<(u32, u32)> = vec![];
+
+    info!("Enumerating positions...");
+    // a position in `ranks` and `index`, its value and counters, and about
+    // one move to it
+    let state_size = 2 * size_of::<u64>()
+        + size_of::<u32>()
+        + size_of::<Option<Value>>()
+        + 2 * size_of::<u16>()
+        + 3 * size_of::<u32>();
+    progress.start("enumerate", 0, state_size as u64);
+    let (root, _) = GameState::new(root.board, root.is_player_1).canonical();
+    table.index.insert(rank(&root), 0);
+    table.ranks.push(rank(&root));
+    // `ranks` is filled in breadth-first order, so it is its own queue
+    let mut expanded: usize = 0;
+    while expanded < table.ranks.len() {
+        let state = unrank(table.ranks[expanded]);
+        let parent = expanded as u32;
+        expanded += 1;
+        let moves = children(&state);
+        let mut value = if moves.is_empty() {
+            Some(Value::Loss(0))
+        } else {
+            None
+        };
+        let mut count = 0;
+        let mut longest = 0;
+        for child in moves {
+            match terminal_value(child.winner, state.is_player_1) {
+                Some(Value::Loss(n)) => longest = n.max(longest),
+                Some(terminal) => value = Some(terminal),
+                None => {
+                    let (child, _) = GameState::new(child.board, !state.is_player_1).canonical();
+                    let child_rank = rank(&child);
+                    let next = table.ranks.len() as u32;
+                    let child = *table.index.entry(child_rank).or_insert(next);
+                    if child == next {
+                        table.ranks.push(child_rank);
+                    }
+                    edges.push((child, parent));
+                    count += 1;
+                }
+            }
+        }
+        if value.is_none() && longest > 0 && count == 0 {
+            value = Some(Value::Loss(longest));
+        }
+        values.push(value);
+        unsettled.push(count);
+        longest_loss.push(longest);
+        progress.add(1);
+    }
+    info!("{} positions reachable", table.ranks.len());
+
+    // the parents of each position, grouped by child
+    edges.sort_unstable();
+    let mut first_parent = vec![0; table.ranks.len() + 1];
+    for (child, _) in edges.iter() {
+        first_parent[*child as usize + 1] += 1;
+    }
+    for i in 0..table.ranks.len() {
+        first_parent[i + 1] += first_parent[i];
+    }
+    let parents: Vec<u32> = edges.into_iter().map(|(_, parent)| parent).collect();
+
+    // positions by the length of their value, settled but not yet passed on
+    // to their parents
+    let mut by_length: Vec<Vec<u32>> = vec![];
+    for (i, value) in values.iter().enumerate() {
+        if let Some(Value::Win(n) | Value::Loss(n)) = value {
+            push_at(&mut by_length, *n, i as u32);
+        }
+    }
+    let mut length = 0;
+    while length < by_length.len() {
+        progress.start("length", length as u32, 0);
+        let settled = std::mem::take(&mut by_length[length]);
+        progress.add(settled.len() as u64);
+        for child in settled {
+            let child = child as usize;
+            let value = values[child].map(Value::parent);
+            for parent in parents[first_parent[child]..first_parent[child + 1]].iter() {
+                let parent = *parent as usize;
+                if values[parent].is_some() {
+                    continue;
+                }
+                match value {
+                    Some(Value::Win(n)) => {
+                        values[parent] = value;
+                        push_at(&mut by_length, n, parent as u32);
+                    }
+                    Some(Value::Loss(n)) => {
+                        unsettled[parent] -= 1;
+                        longest_loss[parent] = longest_loss[parent].max(n);
+                        if unsettled[parent] == 0 {
+                            let n = longest_loss[parent];
+                            values[parent] = Some(Value::Loss(n));
+                            push_at(&mut by_length, n, parent as u32);
+                        }
+                    }
+                    _ => unreachable!("only wins and losses are settled"),
+                }
+            }
+        }
+        length += 1;
+    }
+
+    progress.start("draw", 0, 0);
+    progress.add(values.iter().filter(|value| value.is_none()).count() as u64);
+    table.values = values
+        .into_iter()
+        .map(|value| value.unwrap_or(Value::Draw))
+        .collect();
+    progress.finish();
+    table
+}
+
+fn push_at(by_length: &mut Vec<Vec<u32>>, length: u16, i: u32) {
+    let length = length as usize;
+    if by_length.len() <= length {
+        by_length.resize(length + 1, vec![]);
+    }
+    by_length[length].push(i);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use game_state::GameState;
+    use progress::Progress;
+    use retrograde::{solve_with, Value};
+    use structs::LegalMove;
+
+    // lions and chicks, the other pieces in hand
+    const ROOT: &str = "1L1/1C1/1c1/1l1 w EGeg";
+
+    // The game without drops, small enough from `ROOT` to be searched without
+    // any retrograde bookkeeping.
+    fn board_moves(state: &GameState) -> Vec<LegalMove> {
+        state
+            .legal_children()
+            .into_iter()
+            .filter(|child| !child.next_move.is_drop())
+            .collect()
+    }
+
+    // Value of a move within `depth` plies, `None` if it takes longer.
+    fn move_value(
+        child: &LegalMove,
+        is_player_1: bool,
+        within: &HashMap<GameState, Value>,
+    ) -> Option<Value> {
+        match child.winner {
+            Some(player_one_won) if player_one_won == is_player_1 => Some(Value::Win(1)),
+            Some(_) => Some(Value::Loss(2)),
+            None => within
+                .get(&GameState::new(child.board, !is_player_1))
+                .map(|value| value.parent()),
+        }
+    }
+
+    // Values of the positions decided within `depth` plies, from the ones
+    // decided within `depth - 1`.
+    fn minimax_step(
+        states: &[GameState],
+        depth: u16,
+        within: &HashMap<GameState, Value>,
+    ) -> HashMap<GameState, Value> {
+        let mut next = HashMap::new();
+        for state in states {
+            let mut best: Option<Value> = None;
+            let mut all_known = true;
+            let moves = board_moves(state);
+            if moves.is_empty() {
+                best = Some(Value::Loss(0));
+            }
+            for child in moves.iter() {
+                match move_value(child, state.is_player_1, within) {
+                    Some(value) if best.is_none_or(|b| value.is_better_than(b)) => {
+                        best = Some(value)
+                    }
+                    Some(_) => {}
+                    None => all_known = false,
+                }
+            }
+            let value = match best {
+                Some(Value::Win(n)) if n <= depth => best,
+                Some(Value::Loss(n)) if n <= depth && all_known => best,
+                _ => None,
+            };
+            if let Some(value) = value {
+                next.insert(*state, value);
+            }
+        }
+        next
+    }
+
+    // Every position of the game without drops from `root`, without using
+    // symmetries, and their values by increasing the depth of a minimax
+    // search until it decides nothing more.
+    fn minimax(root: GameState) -> (Vec<GameState>, HashMap<GameState, Value>) {
+        let mut states = vec![root];
+        let mut seen = HashSet::new();
+        seen.insert(root);
+        let mut i = 0;
+        while i < states.len() {
+            let state = states[i];
+            i += 1;
+            for child in board_moves(&state) {
+                let child_state = GameState::new(child.board, !state.is_player_1);
+                if child.winner.is_none() && seen.insert(child_state) {
+                    states.push(child_state);
+                }
+            }
+        }
+        let mut within = HashMap::new();
+        let mut depth = 0;
+        // a value is at most one ply longer than one found at the depth
+        // before, so two depths without news are the end of it
+        let mut quiet = 0;
+        while quiet < 2 {
+            let next = minimax_step(&states, depth, &within);
+            quiet = if next.len() == within.len() {
+                quiet + 1
+            } else {
+                0
+            };
+            within = next;
+            depth += 1;
+        }
+        (states, within)
+    }
+
+    #[test]
+    fn game_without_drops_matches_minimax() {
+        let root: GameState = ROOT.parse().unwrap();
+        let table = solve_with(root, &mut Progress::new("test"), board_moves);
+        let (states, values) = minimax(root);
+        for state in states.iter() {
+            let expected = values.get(state).copied().unwrap_or(Value::Draw);
+            assert_eq!(table.value(state), Some(expected), "{}", state);
+        }
+        // wins, losses and draws all come up
+        assert!(!values.is_empty() && values.len() < states.len());
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use eval::{Evaluator, Standard};
+use game_state::GameState;
+use next_move::NextMove;
+use structs::LegalMove;
+
+// Score of a win on the spot. A win `n` plies away scores `WIN_SCORE - n` so
+// that quicker wins and slower losses are preferred.
+pub const WIN_SCORE: i32 = 100_000;
+const INFINITY: i32 = WIN_SCORE + 1;
+const WIN_THRESHOLD: i32 = WIN_SCORE - 1_000;
+// Evaluations are probabilities, spread over [-EVAL_SCALE, EVAL_SCALE].
+const EVAL_SCALE: f32 = 500.0;
+
+/// When `Searcher::search` has to stop deepening.
+#[derive(Debug, Copy, Clone)]
+pub struct SearchLimits {
+    pub max_depth: u8,
+    pub max_nodes: Option<u64>,
+    pub max_time: Option<Duration>,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            max_depth: 12,
+            max_nodes: None,
+            max_time: None,
+        }
+    }
+}
+
+/// Outcome of the last completed iteration of a search. `score` is from the
+/// point of view of the side to move at the root.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<NextMove>,
+    pub score: i32,
+    pub depth: u8,
+    pub nodes: u64,
+    pub pv: Vec<NextMove>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TtEntry {
+    depth: u8,
+    score: i32,
+    bound: Bound,
+    best_move: Option<NextMove>,
+}
+
+// Mate scores are stored relative to the node they were found at.
+fn to_tt(score: i32, ply: u8) -> i32 {
+    if score > WIN_THRESHOLD {
+        score + ply as i32
+    } else if score < -WIN_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_tt(score: i32, ply: u8) -> i32 {
+    if score > WIN_THRESHOLD {
+        score - ply as i32
+    } else if score < -WIN_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Iterative-deepening alpha-beta search (negamax) with a transposition
+/// table keyed on the position and the side to move. The table is kept
+/// between searches until `clear` is called.
+pub struct Searcher {
+    pub limits: SearchLimits,
+    evaluator: Box<dyn Evaluator>,
+    tt: HashMap<GameState, TtEntry>,
+    nodes: u64,
+    start: Instant,
+    stopped: bool,
+}
+
+impl Searcher {
+    pub fn new(limits: SearchLimits) -> Searcher {
+        Searcher::with_evaluator(limits, Box::new(Standard))
+    }
+
+    pub fn with_evaluator(limits: SearchLimits, evaluator: Box<dyn Evaluator>) -> Searcher {
+        Searcher {
+            limits,
+            evaluator,
+            tt: HashMap::new(),
+            nodes: 0,
+            start: Instant::now(),
+            stopped: false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tt.clear();
+    }
+
+    pub fn search(&mut self, root: GameState) -> SearchResult {
+        self.nodes = 0;
+        self.start = Instant::now();
+        self.stopped = false;
+
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: vec![],
+        };
+        for depth in 1..=self.limits.max_depth {
+            let score = self.negamax(root, depth, 0, -INFINITY, INFINITY);
+            if self.stopped && result.best_move.is_some() {
+                break;
+            }
+            result.score = score;
+            result.depth = depth;
+            result.pv = self.principal_variation(root, depth);
+            result.best_move = result.pv.first().copied();
+            if self.stopped || score.abs() > WIN_THRESHOLD {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result
+    }
+
+    fn out_of_budget(&self) -> bool {
+        self.limits.max_nodes.is_some_and(|max| self.nodes >= max)
+            || (self.nodes.is_multiple_of(1024)
+                && self
+                    .limits
+                    .max_time
+                    .is_some_and(|max| self.start.elapsed() >= max))
+    }
+
+    fn principal_variation(&self, root: GameState, depth: u8) -> Vec<NextMove> {
+        let mut pv = vec![];
+        let mut state = root;
+        while pv.len() < depth as usize {
+            let next_move = match self.tt.get(&state).and_then(|entry| entry.best_move) {
+                Some(next_move) => next_move,
+                None => break,
+            };
+            pv.push(next_move);
+            let child = state
+                .legal_children()
+                .into_iter()
+                .find(|child| child.next_move == next_move);
+            match child {
+                Some(child) if child.winner.is_none() => {
+                    state = GameState::new(child.board, !state.is_player_1)
+                }
+                _ => break,
+            }
+        }
+        pv
+    }
+
+    fn negamax(&mut self, state: GameState, depth: u8, ply: u8, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_budget() {
+            self.stopped = true;
+            return 0;
+        }
+
+        let alpha_orig = alpha;
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.get(&state) {
+            tt_move = entry.best_move;
+            if entry.depth >= depth {
+                let score = from_tt(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut children = state.legal_children();
+        if children.is_empty() {
+            return -(WIN_SCORE - ply as i32);
+        }
+        if children
+            .iter()
+            .any(|child| child.winner == Some(state.is_player_1))
+        {
+            return WIN_SCORE - (ply as i32 + 1);
+        }
+        if depth == 0 {
+            return ((self.evaluator.evaluate(&state) * 2.0 - 1.0) * EVAL_SCALE) as i32;
+        }
+
+        // best move from an earlier iteration first, moves losing on the spot last
+        let order = |child: &LegalMove| {
+            if Some(child.next_move) == tt_move {
+                0
+            } else if child.winner.is_some() {
+                2
+            } else {
+                1
+            }
+        };
+        children.sort_by_key(order);
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for child in children.iter() {
+            let score = if child.winner.is_some() {
+                -(WIN_SCORE - (ply as i32 + 2))
+            } else {
+                -self.negamax(
+                    GameState::new(child.board, !state.is_player_1),
+                    depth - 1,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                )
+            };
+            if self.stopped {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(child.next_move);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score <= alpha_orig {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.tt.insert(
+            state,
+            TtEntry {
+                depth,
+                score: to_tt(best_score, ply),
+                bound,
+                best_move,
+            },
+        );
+        best_score
+    }
+}
@@ -12,6 +12,61 @@ pub enum GameResult {
     Intermediate(Vec<(NextMove, Board)>),
 }
 
+/// A legal move, the board it leads to and whether it ends the game.
+///
+/// `winner` follows `Board::has_winner`: `Some(true)` if player 1 won,
+/// `Some(false)` if player 2 won, `None` if the game goes on.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LegalMove {
+    pub next_move: NextMove,
+    pub board: Board,
+    pub winner: Option<bool>,
+}
+
+/// Why a game ended.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Termination {
+    // a lion was taken, or left where the opponent takes it next move
+    LionCaptured,
+    // a lion reached the far row and cannot be taken there
+    Try,
+    // the side to move has no legal move
+    NoLegalMoves,
+    Repetition,
+    MoveLimit,
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Termination::LionCaptured => write!(f, "lion captured"),
+            Termination::Try => write!(f, "successful try"),
+            Termination::NoLegalMoves => write!(f, "no legal moves"),
+            Termination::Repetition => write!(f, "repetition"),
+            Termination::MoveLimit => write!(f, "move limit"),
+        }
+    }
+}
+
+/// How a game ended: the winner (`Some(true)` for player 1, `None` for a
+/// draw), the reason and the move that ended it, if there was one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GameOutcome {
+    pub winner: Option<bool>,
+    pub termination: Termination,
+    pub last_move: Option<NextMove>,
+}
+
+impl Display for GameOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.winner {
+            Some(true) => write!(f, "White wins ({})", self.termination),
+            Some(false) => write!(f, "Black wins ({})", self.termination),
+            None => write!(f, "Draw ({})", self.termination),
+        }
+    }
+}
+
 pub enum Calc<G> {
     GameResult(G),
     Proba((NextMove, f32)),
@@ -217,6 +272,7 @@ pub enum GameError {
     InavlidPiece,
     InvalidMove,
     IllegalMove,
+    InvalidNotation,
 }
 
 impl Display for GameError {
@@ -231,6 +287,7 @@ impl Display for GameError {
             GameError::InavlidPiece => write!(f, "Invalid piece"),
             GameError::InvalidMove => write!(f, "Invalid move"),
             GameError::EmptyCemetary => write!(f, "Empty Cemetary"),
+            GameError::InvalidNotation => write!(f, "Invalid notation"),
         }
     }
 }
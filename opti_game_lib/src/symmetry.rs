@@ -0,0 +1,150 @@
+use board::Board;
+use game_state::GameState;
+use next_move::NextMove;
+use piece::{Piece, EMPTY};
+use structs::Position;
+
+/// One of the symmetries of the game: a left-right mirror, a colour flip
+/// (the board turned by 180° with the two players swapping their pieces and
+/// the turn), or both. Each one is its own inverse and they commute, so the
+/// transform that canonicalized a position also maps the canonical position
+/// and its moves back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Transform {
+    pub mirror: bool,
+    pub flip: bool,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        mirror: false,
+        flip: false,
+    };
+
+    pub fn inverse(self) -> Transform {
+        self
+    }
+
+    // Applies `self` then `other`.
+    pub fn then(self, other: Transform) -> Transform {
+        Transform {
+            mirror: self.mirror != other.mirror,
+            flip: self.flip != other.flip,
+        }
+    }
+
+    pub fn position(self, position: Position) -> Position {
+        if position == Position::Dead {
+            return position;
+        }
+        let (mut x, mut y) = <&Position as Into<(u8, u8)>>::into(&position);
+        // turning the board mirrors the files too
+        if self.mirror != self.flip {
+            x = 2 - x;
+        }
+        if self.flip {
+            y = 3 - y;
+        }
+        Position::from((x, y))
+    }
+
+    pub fn piece(self, piece: Piece) -> Piece {
+        if !self.flip || piece == EMPTY {
+            return piece;
+        }
+        // player 1 pieces are odd, their player 2 twin is the next id
+        if piece.0 % 2 == 1 {
+            Piece(piece.0 + 1)
+        } else {
+            Piece(piece.0 - 1)
+        }
+    }
+
+    pub fn board(self, board: Board) -> Board {
+        if self == Transform::IDENTITY {
+            return board;
+        }
+        let mut state = board.get_state();
+        for (piece, pos) in state.iter_mut() {
+            *piece = self.piece(*piece);
+            *pos = self.position(*pos);
+        }
+        let mut transformed = Board::new_empty();
+        transformed.put_state(state);
+        transformed
+    }
+
+    pub fn state(self, state: GameState) -> GameState {
+        GameState {
+            board: self.board(state.board),
+            is_player_1: state.is_player_1 != self.flip,
+            ply: state.ply,
+        }
+    }
+
+    pub fn next_move(self, next_move: NextMove) -> NextMove {
+        NextMove::new(
+            self.piece(next_move.piece()),
+            self.position(next_move.old_position()),
+            self.position(next_move.new_position()),
+        )
+    }
+}
+
+impl Board {
+    pub fn mirror(&self) -> Board {
+        Transform {
+            mirror: true,
+            flip: false,
+        }
+        .board(*self)
+    }
+
+    // The same position seen from the other side of the table: player 2's
+    // pieces become player 1's and the other way round.
+    pub fn flip_colours(&self) -> Board {
+        Transform {
+            mirror: false,
+            flip: true,
+        }
+        .board(*self)
+    }
+
+    // The smaller of the board and its mirror image, with the transform
+    // leading to it. The side to move stays the same, so this is what tables
+    // keyed on a board and split by side can use.
+    pub fn canonical(&self) -> (Board, Transform) {
+        let mirrored = self.mirror();
+        if mirrored.0 < self.0 {
+            (
+                mirrored,
+                Transform {
+                    mirror: true,
+                    flip: false,
+                },
+            )
+        } else {
+            (*self, Transform::IDENTITY)
+        }
+    }
+}
+
+impl GameState {
+    // Representative of the up to four positions equivalent to this one:
+    // player 1 to move, and the smaller board of the two mirror images.
+    pub fn canonical(&self) -> (GameState, Transform) {
+        let flip = Transform {
+            mirror: false,
+            flip: !self.is_player_1,
+        };
+        let (board, mirror) = flip.board(self.board).canonical();
+        (
+            GameState {
+                board,
+                is_player_1: true,
+                ply: self.ply,
+            },
+            flip.then(mirror),
+        )
+    }
+}
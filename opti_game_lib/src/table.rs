@@ -0,0 +1,483 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use board::Board;
+use next_move::NextMove;
+use retrograde::Value;
+
+// Binary move tables.
+//
+// A table is a 32-byte header followed by 12-byte records sorted by board,
+// all little-endian:
+//
+// ```text
+// header   0  magic "DBTB"
+//          4  version, u16
+//          6  rule set, u8
+//          7  depth the table was computed to, 0 for an exact table
+//          8  side to move in the table, 0 for player 1, 1 for player 2
+//          9  flags, `FLAG_MIRROR`, `FLAG_COLOUR_FLIP` and `FLAG_NO_VALUES`
+//         10  reserved, zero
+//         16  number of records, u64
+//         24  FNV-1a hash of the records, u64
+// record   0  board, u64
+//          8  move, u16, 0 when there is none
+//         10  value, u16: the result in the two high bits and a number in
+//             the other 14, see `TableValue`
+// ```
+
+pub const MAGIC: &[u8; 4] = b"DBTB";
+pub const VERSION: u16 = 1;
+pub const HEADER_SIZE: usize = 32;
+pub const RECORD_SIZE: usize = 12;
+
+// The rules as implemented by `Board`.
+pub const RULES_STANDARD: u8 = 0;
+pub const DEPTH_EXACT: u8 = 0;
+pub const SIDE_PLAYER_1: u8 = 0;
+pub const SIDE_PLAYER_2: u8 = 1;
+// Only the smaller of a board and its mirror image is stored.
+pub const FLAG_MIRROR: u8 = 1;
+// Positions are stored with player 1 to move, see `GameState::canonical`.
+pub const FLAG_COLOUR_FLIP: u8 = 2;
+// Records hold moves only and their value is zero, as for a table converted
+// from `generate`'s text tables.
+pub const FLAG_NO_VALUES: u8 = 4;
+
+/// What a table knows about a position, for its side to move.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TableValue {
+    // chances of winning from a depth-limited table
+    Probability(f32),
+    Exact(Value),
+}
+
+const MAX_NUMBER: u16 = 0x3fff;
+
+impl TableValue {
+    pub fn encode(self) -> u16 {
+        match self {
+            TableValue::Probability(p) => (p.clamp(0.0, 1.0) * MAX_NUMBER as f32).round() as u16,
+            TableValue::Exact(Value::Win(n)) => 1 << 14 | n.min(MAX_NUMBER),
+            TableValue::Exact(Value::Loss(n)) => 2 << 14 | n.min(MAX_NUMBER),
+            TableValue::Exact(Value::Draw) => 3 << 14,
+        }
+    }
+
+    pub fn decode(value: u16) -> TableValue {
+        let number = value & MAX_NUMBER;
+        match value >> 14 {
+            0 => TableValue::Probability(number as f32 / MAX_NUMBER as f32),
+            1 => TableValue::Exact(Value::Win(number)),
+            2 => TableValue::Exact(Value::Loss(number)),
+            _ => TableValue::Exact(Value::Draw),
+        }
+    }
+}
+
+impl Display for TableValue {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            // every digit needed to read back the same `f32`, so that a
+            // table goes to text and back without changing
+            TableValue::Probability(p) => write!(f, "{}", p),
+            TableValue::Exact(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TableEntry {
+    pub board: Board,
+    pub next_move: NextMove,
+    pub value: TableValue,
+}
+
+impl TableEntry {
+    fn to_bytes(self) -> [u8; RECORD_SIZE] {
+        let mut bytes = [0u8; RECORD_SIZE];
+        bytes[0..8].copy_from_slice(&self.board.0.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.next_move.0.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.value.encode().to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> TableEntry {
+        TableEntry {
+            board: Board(read_u64(&bytes[0..8])),
+            next_move: NextMove(u16::from_le_bytes([bytes[8], bytes[9]])),
+            value: TableValue::decode(u16::from_le_bytes([bytes[10], bytes[11]])),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TableHeader {
+    pub version: u16,
+    pub rules: u8,
+    pub depth: u8,
+    pub side: u8,
+    pub flags: u8,
+    pub count: u64,
+    pub checksum: u64,
+}
+
+impl TableHeader {
+    // Header for a table still to be written, `count` and `checksum` are
+    // filled in by `write_table`.
+    pub fn new(depth: u8, side: u8, flags: u8) -> TableHeader {
+        TableHeader {
+            version: VERSION,
+            rules: RULES_STANDARD,
+            depth,
+            side,
+            flags,
+            count: 0,
+            checksum: 0,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6] = self.rules;
+        bytes[7] = self.depth;
+        bytes[8] = self.side;
+        bytes[9] = self.flags;
+        bytes[16..24].copy_from_slice(&self.count.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<TableHeader, TableError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(TableError::Truncated);
+        }
+        if &bytes[0..4] != MAGIC {
+            return Err(TableError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(TableError::UnsupportedVersion(version));
+        }
+        Ok(TableHeader {
+            version,
+            rules: bytes[6],
+            depth: bytes[7],
+            side: bytes[8],
+            flags: bytes[9],
+            count: read_u64(&bytes[16..24]),
+            checksum: read_u64(&bytes[24..32]),
+        })
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut array = [0u8; 8];
+    array.copy_from_slice(bytes);
+    u64::from_le_bytes(array)
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |hash, b| (hash ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    Checksum,
+    // line number in a text table
+    InvalidLine(usize),
+}
+
+impl Display for TableError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            TableError::Io(e) => write!(f, "{}", e),
+            TableError::BadMagic => write!(f, "Not a table file"),
+            TableError::UnsupportedVersion(v) => write!(f, "Unsupported table version {}", v),
+            TableError::Truncated => write!(f, "Truncated table file"),
+            TableError::Checksum => write!(f, "Table checksum mismatch"),
+            TableError::InvalidLine(line) => write!(f, "Invalid table line {}", line),
+        }
+    }
+}
+
+impl Error for TableError {}
+
+impl From<io::Error> for TableError {
+    fn from(e: io::Error) -> Self {
+        TableError::Io(e)
+    }
+}
+
+// Sorts `entries` by board, keeps the first entry of each board and writes
+// them under `header`.
+pub fn write_table<P: AsRef<Path>>(
+    path: P,
+    header: TableHeader,
+    mut entries: Vec<TableEntry>,
+) -> io::Result<()> {
+    entries.sort_by_key(|entry| entry.board.0);
+    let mut writer = TableWriter::create(path, header)?;
+    for entry in entries {
+        writer.push(entry)?;
+    }
+    writer.finish()
+}
+
+/// Writes a table from entries that come sorted by board, without holding
+/// them. The header is written again with the count and checksum when the
+/// table is finished.
+pub struct TableWriter {
+    f: BufWriter<File>,
+    header: TableHeader,
+    last: Option<u64>,
+}
+
+impl TableWriter {
+    pub fn create<P: AsRef<Path>>(path: P, header: TableHeader) -> io::Result<TableWriter> {
+        let mut f = BufWriter::new(File::create(path)?);
+        f.write_all(&header.to_bytes())?;
+        Ok(TableWriter {
+            f,
+            header: TableHeader {
+                count: 0,
+                checksum: FNV_OFFSET,
+                ..header
+            },
+            last: None,
+        })
+    }
+
+    // Adds `entry`, unless the last one added has the same board.
+    pub fn push(&mut self, entry: TableEntry) -> io::Result<()> {
+        if self.last == Some(entry.board.0) {
+            return Ok(());
+        }
+        debug_assert!(self.last.is_none_or(|last| last < entry.board.0));
+        self.last = Some(entry.board.0);
+        let bytes = entry.to_bytes();
+        self.header.count += 1;
+        self.header.checksum = fnv1a(self.header.checksum, &bytes);
+        self.f.write_all(&bytes)
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.f.seek(SeekFrom::Start(0))?;
+        self.f.write_all(&self.header.to_bytes())?;
+        self.f.flush()
+    }
+}
+
+// Whether the file at `path` starts like a binary table.
+pub fn is_table<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let mut magic = [0u8; 4];
+    match io::Read::read_exact(&mut File::open(path)?, &mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// A table file mapped in memory. Opening it only reads the header; records
+/// are paged in by the lookups that touch them.
+pub struct TableReader {
+    header: TableHeader,
+    mmap: Mmap,
+}
+
+impl TableReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<TableReader, TableError> {
+        let file = File::open(path)?;
+        // the file must not be changed while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        let header = TableHeader::from_bytes(&mmap)?;
+        // no file is long enough for a count whose size overflows
+        let size = header
+            .count
+            .checked_mul(RECORD_SIZE as u64)
+            .ok_or(TableError::Truncated)?;
+        if ((mmap.len() - HEADER_SIZE) as u64) < size {
+            return Err(TableError::Truncated);
+        }
+        Ok(TableReader { header, mmap })
+    }
+
+    pub fn header(&self) -> TableHeader {
+        self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.header.count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.count == 0
+    }
+
+    // Reads every record to check them against the header's checksum.
+    pub fn verify(&self) -> Result<(), TableError> {
+        let records = &self.mmap[HEADER_SIZE..HEADER_SIZE + self.len() * RECORD_SIZE];
+        if fnv1a(FNV_OFFSET, records) == self.header.checksum {
+            Ok(())
+        } else {
+            Err(TableError::Checksum)
+        }
+    }
+
+    pub fn entry(&self, i: usize) -> TableEntry {
+        let start = HEADER_SIZE + i * RECORD_SIZE;
+        TableEntry::from_bytes(&self.mmap[start..start + RECORD_SIZE])
+    }
+
+    fn board_at(&self, i: usize) -> u64 {
+        let start = HEADER_SIZE + i * RECORD_SIZE;
+        read_u64(&self.mmap[start..start + 8])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = TableEntry> + '_ {
+        (0..self.len()).map(move |i| self.entry(i))
+    }
+
+    // Interpolation search on the board, which lands close to the record in
+    // a few probes as packed boards are spread fairly evenly, falling back to
+    // a binary search on the remaining range if it does not.
+    pub fn find(&self, board: Board) -> Option<TableEntry> {
+        if self.is_empty() {
+            return None;
+        }
+        let key = board.0;
+        let (mut lo, mut hi) = (0, self.len() - 1);
+        for _ in 0..8 {
+            let (lo_key, hi_key) = (self.board_at(lo), self.board_at(hi));
+            if key < lo_key || key > hi_key {
+                return None;
+            }
+            if lo_key == hi_key {
+                break;
+            }
+            let mid = lo
+                + ((key - lo_key) as u128 * (hi - lo) as u128 / (hi_key - lo_key) as u128) as usize;
+            match self.board_at(mid).cmp(&key) {
+                std::cmp::Ordering::Equal => return Some(self.entry(mid)),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater if mid == 0 => return None,
+                std::cmp::Ordering::Greater => hi = mid - 1,
+            }
+            if lo > hi {
+                return None;
+            }
+        }
+
+        while lo <= hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.board_at(mid).cmp(&key) {
+                std::cmp::Ordering::Equal => return Some(self.entry(mid)),
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater if mid == 0 => return None,
+                std::cmp::Ordering::Greater => hi = mid - 1,
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use board::Board;
+    use next_move::NextMove;
+    use retrograde::Value;
+    use table::{
+        write_table, TableEntry, TableError, TableHeader, TableReader, TableValue, FLAG_MIRROR,
+        HEADER_SIZE, MAX_NUMBER, SIDE_PLAYER_1,
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("table-test-{}-{}.bin", std::process::id(), name))
+    }
+
+    fn entries() -> Vec<TableEntry> {
+        (1..200u64)
+            .map(|i| TableEntry {
+                board: Board(i.wrapping_mul(0x9e3779b97f4a7c15)),
+                next_move: NextMove(i as u16),
+                value: TableValue::Exact(if i % 2 == 0 {
+                    Value::Win(i as u16)
+                } else {
+                    Value::Loss(i as u16)
+                }),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn probabilities_survive_text() {
+        for number in 0..=MAX_NUMBER {
+            let text = TableValue::decode(number).to_string();
+            let p: f32 = text.parse().unwrap();
+            assert_eq!(TableValue::Probability(p).encode(), number, "{}", text);
+        }
+    }
+
+    #[test]
+    fn write_open_find() {
+        let path = temp_path("round-trip");
+        let header = TableHeader::new(0, SIDE_PLAYER_1, FLAG_MIRROR);
+        write_table(&path, header, entries()).unwrap();
+        let reader = TableReader::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(reader.len(), entries().len());
+        assert_eq!(reader.header().flags, FLAG_MIRROR);
+        reader.verify().unwrap();
+        for entry in entries() {
+            assert_eq!(reader.find(entry.board), Some(entry));
+        }
+        assert_eq!(reader.find(Board(0)), None);
+        assert_eq!(reader.find(Board(u64::MAX)), None);
+    }
+
+    #[test]
+    fn damaged_tables_are_rejected() {
+        let path = temp_path("damaged");
+        write_table(&path, TableHeader::new(0, SIDE_PLAYER_1, 0), entries()).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        let truncated = TableReader::open(&path);
+
+        let mut corrupted = bytes.clone();
+        corrupted[HEADER_SIZE + 5] ^= 1;
+        fs::write(&path, &corrupted).unwrap();
+        let corrupted = TableReader::open(&path).unwrap().verify();
+
+        // a record count whose size in bytes overflows
+        let mut huge = bytes.clone();
+        huge[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, &huge).unwrap();
+        let huge = TableReader::open(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(truncated, Err(TableError::Truncated)));
+        assert!(matches!(corrupted, Err(TableError::Checksum)));
+        assert!(matches!(huge, Err(TableError::Truncated)));
+    }
+}
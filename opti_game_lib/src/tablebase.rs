@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use board::Board;
+use game_state::GameState;
+use next_move::NextMove;
+use retrograde::Value;
+use symmetry::Transform;
+use table::{
+    is_table, TableEntry, TableError, TableHeader, TableReader, TableValue, FLAG_COLOUR_FLIP,
+    FLAG_MIRROR, FLAG_NO_VALUES, SIDE_PLAYER_1,
+};
+
+enum Source {
+    Binary(TableReader),
+    // sorted by board
+    Text(Vec<(Board, NextMove, Option<TableValue>)>),
+}
+
+/// Read access to a table written by `dobustu-precomp`, whatever its format:
+/// a binary table, a `BOARD MOVE` text table from `generate`, possibly with
+/// the probability after the move, or a `BOARD MOVE VALUE` text table from
+/// `solve`.
+///
+/// Positions are looked up in the orientation the table stores them in and
+/// moves are given back in the caller's orientation.
+pub struct Tablebase {
+    source: Source,
+    // `None` when the table does not say, as for `generate`'s text tables
+    side: Option<bool>,
+    flags: u8,
+}
+
+fn parse_value(text: &str) -> Option<TableValue> {
+    let value = if text == "D" {
+        Value::Draw
+    } else if let Some(n) = text.strip_prefix('W') {
+        Value::Win(n.parse().ok()?)
+    } else if let Some(n) = text.strip_prefix('L') {
+        Value::Loss(n.parse().ok()?)
+    } else {
+        return text.parse().ok().map(TableValue::Probability);
+    };
+    Some(TableValue::Exact(value))
+}
+
+impl Tablebase {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Tablebase, TableError> {
+        let path = path.as_ref();
+        if is_table(path)? {
+            let reader = TableReader::open(path)?;
+            let header = reader.header();
+            return Ok(Tablebase {
+                source: Source::Binary(reader),
+                side: Some(header.side == SIDE_PLAYER_1),
+                flags: header.flags,
+            });
+        }
+
+        let mut entries = vec![];
+        let mut solved = false;
+        let mut valued = false;
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            let invalid = || TableError::InvalidLine(i + 1);
+            if fields.len() < 2 || fields.len() > 3 {
+                return Err(invalid());
+            }
+            let board = u64::from_str_radix(fields[0], 16).map_err(|_| invalid())?;
+            let next_move = u16::from_str_radix(fields[1], 16).map_err(|_| invalid())?;
+            let value = match fields.get(2) {
+                Some(value) => Some(parse_value(value).ok_or_else(invalid)?),
+                None => None,
+            };
+            solved |= matches!(value, Some(TableValue::Exact(_)));
+            valued |= value.is_some();
+            entries.push((Board(board), NextMove(next_move), value));
+        }
+        entries.sort_by_key(|(board, _, _)| board.0);
+
+        // `solve` only writes canonical positions, `generate` one of each pair
+        // of mirror images
+        let flags = if solved {
+            FLAG_MIRROR | FLAG_COLOUR_FLIP
+        } else {
+            FLAG_MIRROR
+        };
+        Ok(Tablebase {
+            source: Source::Text(entries),
+            side: if solved { Some(true) } else { None },
+            flags: if valued {
+                flags
+            } else {
+                flags | FLAG_NO_VALUES
+            },
+        })
+    }
+
+    // Header of a binary table.
+    pub fn header(&self) -> Option<TableHeader> {
+        match &self.source {
+            Source::Binary(reader) => Some(reader.header()),
+            Source::Text(_) => None,
+        }
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    // Side to move of the stored positions, if the table says.
+    pub fn side(&self) -> Option<bool> {
+        self.side
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.source {
+            Source::Binary(reader) => reader.len(),
+            Source::Text(entries) => entries.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Value of a binary record, none if the table holds moves only.
+    fn value(&self, entry: &TableEntry) -> Option<TableValue> {
+        if self.flags & FLAG_NO_VALUES != 0 {
+            None
+        } else {
+            Some(entry.value)
+        }
+    }
+
+    // Stored entries in the table's own orientation, by board.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (Board, NextMove, Option<TableValue>)> + '_> {
+        match &self.source {
+            Source::Binary(reader) => Box::new(
+                reader
+                    .iter()
+                    .map(move |entry| (entry.board, entry.next_move, self.value(&entry))),
+            ),
+            Source::Text(entries) => Box::new(entries.iter().copied()),
+        }
+    }
+
+    fn find(&self, board: Board) -> Option<(NextMove, Option<TableValue>)> {
+        match &self.source {
+            Source::Binary(reader) => reader
+                .find(board)
+                .map(|entry| (entry.next_move, self.value(&entry))),
+            Source::Text(entries) => entries
+                .binary_search_by_key(&board.0, |(board, _, _)| board.0)
+                .ok()
+                .map(|i| (entries[i].1, entries[i].2)),
+        }
+    }
+
+    // The stored entry for `board` with `is_player_1` to move, and the
+    // transform from the caller's orientation to the stored one.
+    fn lookup(
+        &self,
+        board: &Board,
+        is_player_1: bool,
+    ) -> Option<(NextMove, Option<TableValue>, Transform)> {
+        let state = GameState::new(*board, is_player_1);
+        let mut candidates = vec![];
+        if self.side.is_none_or(|side| side == is_player_1) {
+            candidates.push((*board, Transform::IDENTITY));
+            if self.flags & FLAG_MIRROR != 0 {
+                candidates.push(board.canonical());
+            }
+        }
+        if self.flags & FLAG_COLOUR_FLIP != 0 {
+            let (canonical, transform) = state.canonical();
+            candidates.push((canonical.board, transform));
+        }
+        candidates.into_iter().find_map(|(board, transform)| {
+            self.find(board)
+                .map(|(next_move, value)| (next_move, value, transform))
+        })
+    }
+
+    pub fn best_move(&self, board: &Board, is_player_1: bool) -> Option<NextMove> {
+        let (next_move, _, transform) = self.lookup(board, is_player_1)?;
+        if next_move.0 == 0 {
+            return None;
+        }
+        Some(transform.inverse().next_move(next_move))
+    }
+
+    // Value of the position for its side to move. `None` if it is not in the
+    // table or the table holds moves only, like `generate`'s text tables.
+    pub fn probe(&self, board: &Board, is_player_1: bool) -> Option<TableValue> {
+        self.lookup(board, is_player_1)
+            .and_then(|(_, value, _)| value)
+    }
+
+    pub fn contains(&self, board: &Board, is_player_1: bool) -> bool {
+        self.lookup(board, is_player_1).is_some()
+    }
+}
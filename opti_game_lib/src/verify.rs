@@ -0,0 +1,184 @@
+use std::fmt::{Display, Formatter};
+
+use board::Board;
+use game_state::GameState;
+use next_move::NextMove;
+use retrograde::{terminal_value, Value};
+use structs::GameError;
+use table::TableValue;
+use tablebase::Tablebase;
+
+// Checks of a table against the rules: every stored move must be legal for
+// its board and, for exact values, every value must be the best one over
+// the board's children as stored in the same table, with the stored move
+// reaching it.
+//
+// Only the moves of depth-limited tables are checked. Their probabilities
+// cannot be recomputed from the table, even two plies down: a position
+// reached again two plies later, by a move and its reverse, is back-propagated
+// at both depths, but the table keeps the value of the shallower one while
+// its parents were computed from the deeper one.
+
+#[derive(Debug)]
+pub enum Problem {
+    IllegalMove(NextMove, GameError),
+    // no move stored for a board with legal moves
+    MissingMove,
+    // a child of the board is not in the table
+    MissingChild(Board),
+    WrongValue { stored: Value, expected: Value },
+    // the stored move does not reach the stored value
+    WrongMove { stored: Value, reached: Value },
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub state: GameState,
+    pub problem: Problem,
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Problem::IllegalMove(next_move, e) => write!(f, "illegal move {:X}: {}", next_move, e),
+            Problem::MissingMove => write!(f, "no move stored but the side to move has some"),
+            Problem::MissingChild(board) => write!(f, "child {:X} is not in the table", board.0),
+            Problem::WrongValue { stored, expected } => {
+                write!(f, "value {} but the children give {}", stored, expected)
+            }
+            Problem::WrongMove { stored, reached } => {
+                write!(
+                    f,
+                    "value {} but the stored move reaches {}",
+                    stored, reached
+                )
+            }
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:X} ({} to move): {}",
+            self.state.board.0,
+            if self.state.is_player_1 {
+                "white"
+            } else {
+                "black"
+            },
+            self.problem
+        )?;
+        write!(f, "{}", self.state.board.debug_show_board_string())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checked: usize,
+    // entries with a probability, of which only the move was checked
+    pub moves_only: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+// Value of playing `child` for the side moving, from the table.
+fn child_value(
+    tablebase: &Tablebase,
+    board: Board,
+    winner: Option<bool>,
+    is_player_1: bool,
+) -> Result<Value, Problem> {
+    if let Some(value) = terminal_value(winner, is_player_1) {
+        return Ok(value);
+    }
+    match tablebase.probe(&board, !is_player_1) {
+        Some(TableValue::Exact(value)) => Ok(value.parent()),
+        _ => Err(Problem::MissingChild(board)),
+    }
+}
+
+fn check(
+    tablebase: &Tablebase,
+    state: GameState,
+    next_move: NextMove,
+    value: Option<TableValue>,
+) -> Vec<Problem> {
+    let mut problems = vec![];
+    let children = state.legal_children();
+    if next_move.0 == 0 {
+        if !children.is_empty() {
+            problems.push(Problem::MissingMove);
+        }
+    } else if let Err(e) = state.apply_move(next_move) {
+        problems.push(Problem::IllegalMove(next_move, e));
+    }
+
+    let stored = match value {
+        Some(TableValue::Exact(value)) => value,
+        _ => return problems,
+    };
+    if children.is_empty() {
+        if stored != Value::Loss(0) {
+            problems.push(Problem::WrongValue {
+                stored,
+                expected: Value::Loss(0),
+            });
+        }
+        return problems;
+    }
+
+    let mut best: Option<Value> = None;
+    let mut reached = None;
+    for child in children.iter() {
+        match child_value(tablebase, child.board, child.winner, state.is_player_1) {
+            Ok(value) => {
+                if best.is_none_or(|b| value.is_better_than(b)) {
+                    best = Some(value);
+                }
+                if child.next_move == next_move {
+                    reached = Some(value);
+                }
+            }
+            Err(problem) => problems.push(problem),
+        }
+    }
+    // with children missing, the best value is not known
+    if problems
+        .iter()
+        .any(|p| matches!(p, Problem::MissingChild(_)))
+    {
+        return problems;
+    }
+    if let Some(expected) = best.filter(|expected| *expected != stored) {
+        problems.push(Problem::WrongValue { stored, expected });
+    }
+    if let Some(reached) = reached.filter(|reached| *reached != stored) {
+        problems.push(Problem::WrongMove { stored, reached });
+    }
+    problems
+}
+
+// Checks every entry of `tablebase`, its positions having `is_player_1` to
+// move unless the table says otherwise.
+pub fn verify(tablebase: &Tablebase, is_player_1: bool) -> Report {
+    let is_player_1 = tablebase.side().unwrap_or(is_player_1);
+    let mut report = Report::default();
+    for (board, next_move, value) in tablebase.iter() {
+        let state = GameState::new(board, is_player_1);
+        for problem in check(tablebase, state, next_move, value) {
+            report.issues.push(Issue { state, problem });
+        }
+        report.checked += 1;
+        if let Some(TableValue::Probability(_)) = value {
+            report.moves_only += 1;
+        }
+    }
+    report
+}